@@ -0,0 +1,339 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera_path::{EaseMode, Easing, Repeat},
+    noise::Noise,
+};
+
+/// How a parameter's value changes over simulation time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Animation {
+    Constant(f32),
+    Tween(Tween),
+    Noise(NoiseAnimation),
+    Curve(Curve),
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Animation::Constant(0.0)
+    }
+}
+
+impl Animation {
+    pub fn sample(&self, time: f32) -> f32 {
+        match self {
+            Animation::Constant(value) => *value,
+            Animation::Tween(tween) => tween.sample(time),
+            Animation::Noise(noise) => noise.sample(time),
+            Animation::Curve(curve) => curve.sample(time),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Animation::Constant(_) => "Constant",
+            Animation::Tween(_) => "Tween",
+            Animation::Noise(_) => "Noise",
+            Animation::Curve(_) => "Curve",
+        }
+    }
+}
+
+/// Eased move between two values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tween {
+    pub from: f32,
+    pub to: f32,
+    /// Seconds before the move starts.
+    pub delay: f32,
+    pub duration: f32,
+    pub easing: Easing,
+    pub mode: EaseMode,
+    pub repeat: Repeat,
+}
+
+impl Default for Tween {
+    fn default() -> Self {
+        Tween {
+            from: 0.0,
+            to: 1.0,
+            delay: 0.0,
+            duration: 1.0,
+            easing: Easing::Sine,
+            mode: EaseMode::InOut,
+            repeat: Repeat::Once,
+        }
+    }
+}
+
+impl Tween {
+    pub fn sample(&self, time: f32) -> f32 {
+        let time = self.repeat.wrap(time - self.delay, self.duration);
+        let t = if self.duration > 0.0 {
+            self.easing.apply(self.mode, time / self.duration)
+        } else {
+            1.0
+        };
+        self.from + (self.to - self.from) * t
+    }
+}
+
+/// Noise mapped to `offset..offset + scale`, or integrated over time, e.g.
+/// to turn a noisy speed into an angle.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoiseAnimation {
+    pub noise: Noise,
+    pub scale: f32,
+    pub offset: f32,
+    pub integrate: bool,
+}
+
+impl Default for NoiseAnimation {
+    fn default() -> Self {
+        NoiseAnimation {
+            noise: Noise::default(),
+            scale: 1.0,
+            offset: 0.0,
+            integrate: false,
+        }
+    }
+}
+
+impl NoiseAnimation {
+    pub fn sample(&self, time: f32) -> f32 {
+        let value = if self.integrate {
+            self.noise.integral(time)
+        } else {
+            self.noise.sample(time)
+        };
+        self.offset + self.scale * value
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CurveKey {
+    /// Seconds from the start of the curve.
+    pub time: f32,
+    pub value: f32,
+    /// Curve of the move into this key.
+    #[serde(default)]
+    pub easing: Easing,
+    #[serde(default)]
+    pub mode: EaseMode,
+}
+
+/// Values at keyed times, eased in between.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Curve {
+    /// Sorted by time.
+    pub keys: Vec<CurveKey>,
+    pub repeat: Repeat,
+}
+
+impl Curve {
+    pub fn sample(&self, time: f32) -> f32 {
+        let (Some(first), Some(last)) = (self.keys.first(), self.keys.last()) else {
+            return 0.0;
+        };
+        let time = first.time + self.repeat.wrap(time - first.time, last.time - first.time);
+        let mut from = first;
+        for to in &self.keys[1..] {
+            if time <= to.time {
+                let span = to.time - from.time;
+                let t = if span > 0.0 {
+                    to.easing.apply(to.mode, (time - from.time) / span)
+                } else {
+                    1.0
+                };
+                return from.value + (to.value - from.value) * t;
+            }
+            from = to;
+        }
+        last.value
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub animation: Animation,
+    /// A held parameter keeps its value while the shared clock runs on.
+    held: bool,
+    /// How far the parameter lags the shared clock from being held.
+    offset: f32,
+}
+
+impl Param {
+    fn local_time(&self, time: f32, lead: f32) -> f32 {
+        if self.held {
+            time - self.offset
+        } else {
+            time - self.offset + lead
+        }
+    }
+}
+
+/// Named animated parameters sharing one clock.
+#[derive(Debug, Default)]
+pub struct Params {
+    pub params: Vec<Param>,
+    time: f32,
+}
+
+impl Params {
+    /// Add a parameter, or replace the animation of an existing one.
+    pub fn insert(&mut self, name: &str, animation: Animation) {
+        match self.params.iter_mut().find(|p| p.name == name) {
+            Some(param) => param.animation = animation,
+            None => self.params.push(Param {
+                name: name.to_string(),
+                animation,
+                held: false,
+                offset: 0.0,
+            }),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Animation> {
+        self.params
+            .iter()
+            .find(|p| p.name == name)
+            .map(|p| &p.animation)
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn advance(&mut self, dt: f32) {
+        self.time += dt;
+        for param in self.params.iter_mut().filter(|p| p.held) {
+            param.offset += dt;
+        }
+    }
+
+    /// Hold `name` at its current value, or release it to run on from there.
+    pub fn set_held(&mut self, name: &str, held: bool) {
+        if let Some(param) = self.params.iter_mut().find(|p| p.name == name) {
+            param.held = held;
+        }
+    }
+
+    pub fn seek(&mut self, time: f32) {
+        self.time = time;
+    }
+
+    /// The value of `name` `lead` seconds past the current time.
+    pub fn value(&self, name: &str, lead: f32) -> Option<f32> {
+        self.params
+            .iter()
+            .find(|p| p.name == name)
+            .map(|p| p.animation.sample(p.local_time(self.time, lead)))
+    }
+}
+
+/// The floats of a uniform buffer, some of them driven by parameters.
+#[derive(Debug)]
+pub struct UniformFields {
+    values: Vec<f32>,
+    /// Parameter name and index of the float it drives.
+    bindings: Vec<(String, usize)>,
+}
+
+impl UniformFields {
+    /// `values` are the initial contents, including padding.
+    pub fn new(values: Vec<f32>) -> Self {
+        UniformFields {
+            values,
+            bindings: Vec::new(),
+        }
+    }
+
+    pub fn bind(mut self, param: &str, index: usize) -> Self {
+        assert!(index < self.values.len(), "{param} bound past the uniform");
+        self.bindings.push((param.to_string(), index));
+        self
+    }
+
+    pub fn create_buffer(&self, device: &wgpu::Device, label: &str) -> wgpu::Buffer {
+        use wgpu::util::DeviceExt;
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(&self.values),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        })
+    }
+
+    /// Sample the bound parameters and upload the floats if any changed.
+    /// Fields of missing parameters keep their value.
+    pub fn write(
+        &mut self,
+        queue: &wgpu::Queue,
+        buffer: &wgpu::Buffer,
+        params: &Params,
+        lead: f32,
+    ) {
+        let mut changed = false;
+        for (name, index) in &self.bindings {
+            if let Some(value) = params.value(name, lead) {
+                changed |= self.values[*index] != value;
+                self.values[*index] = value;
+            }
+        }
+        if changed {
+            queue.write_buffer(buffer, 0, bytemuck::cast_slice(&self.values));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(repeat: Repeat) -> Curve {
+        let key = |time, value| CurveKey {
+            time,
+            value,
+            easing: Easing::Linear,
+            mode: EaseMode::InOut,
+        };
+        Curve {
+            keys: vec![key(1.0, 0.0), key(2.0, 2.0), key(2.0, 4.0), key(3.0, 4.0)],
+            repeat,
+        }
+    }
+
+    #[test]
+    fn curve_sample() {
+        let curve = ramp(Repeat::Once);
+        assert_eq!(curve.sample(0.0), 0.0);
+        assert_eq!(curve.sample(1.5), 1.0);
+        // Keys at the same time jump.
+        assert_eq!(curve.sample(2.0), 2.0);
+        assert_eq!(curve.sample(2.5), 4.0);
+        assert_eq!(curve.sample(9.0), 4.0);
+        assert_eq!(ramp(Repeat::Loop).sample(3.5), 1.0);
+        assert_eq!(ramp(Repeat::PingPong).sample(4.5), 1.0);
+        assert_eq!(Curve::default().sample(1.0), 0.0);
+    }
+
+    #[test]
+    fn held_param() {
+        let mut params = Params::default();
+        params.insert("a", Animation::Curve(ramp(Repeat::Once)));
+        params.insert("b", Animation::Curve(ramp(Repeat::Once)));
+        params.advance(1.25);
+        params.set_held("a", true);
+        params.advance(0.5);
+        // A held parameter ignores the lead too.
+        assert_eq!(params.value("a", 0.25), Some(0.5));
+        assert_eq!(params.value("b", 0.0), Some(1.5));
+        params.set_held("a", false);
+        params.advance(0.25);
+        assert_eq!(params.value("a", 0.0), Some(1.0));
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use winit::event::{ModifiersState, MouseButton, VirtualKeyCode};
+
+use crate::resources::load_string;
+
+/// Something the user can do from the keyboard or mouse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    /// Held to turn the camera with the mouse.
+    Look,
+    /// Held to turn orbit drags into pans.
+    Pan,
+    ToggleRotation,
+    CaptureFrame,
+    ToggleRecording,
+    ToggleCameraMode,
+    AddKeyframe,
+    ToggleCameraPath,
+    TogglePause,
+    /// Pause and advance the simulation by one fixed step.
+    SingleStep,
+}
+
+impl Action {
+    pub const ALL: [Action; 16] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::Look,
+        Action::Pan,
+        Action::ToggleRotation,
+        Action::CaptureFrame,
+        Action::ToggleRecording,
+        Action::ToggleCameraMode,
+        Action::AddKeyframe,
+        Action::ToggleCameraPath,
+        Action::TogglePause,
+        Action::SingleStep,
+    ];
+
+    /// Frame capture writes files, so it only exists on native.
+    pub fn is_available(self) -> bool {
+        cfg!(not(target_arch = "wasm32"))
+            || !matches!(self, Action::CaptureFrame | Action::ToggleRecording)
+    }
+
+    /// Held actions last until released, the others fire once per press.
+    pub fn is_held(self) -> bool {
+        matches!(
+            self,
+            Action::MoveForward
+                | Action::MoveBackward
+                | Action::MoveLeft
+                | Action::MoveRight
+                | Action::MoveUp
+                | Action::MoveDown
+                | Action::Look
+                | Action::Pan
+        )
+    }
+}
+
+/// A key or mouse button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Input {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+}
+
+impl std::fmt::Display for Input {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Input::Key(key) => write!(f, "{:?}", key),
+            Input::Mouse(MouseButton::Other(button)) => write!(f, "Mouse {}", button),
+            Input::Mouse(button) => write!(f, "Mouse {:?}", button),
+        }
+    }
+}
+
+/// Modifier keys that must be held for a binding to trigger.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub logo: bool,
+}
+
+impl Modifiers {
+    /// Whether all of these modifiers are held in `state`.
+    pub fn held_in(&self, state: ModifiersState) -> bool {
+        (!self.shift || state.shift())
+            && (!self.ctrl || state.ctrl())
+            && (!self.alt || state.alt())
+            && (!self.logo || state.logo())
+    }
+
+    fn count(&self) -> usize {
+        [self.shift, self.ctrl, self.alt, self.logo]
+            .iter()
+            .filter(|held| **held)
+            .count()
+    }
+}
+
+impl From<ModifiersState> for Modifiers {
+    fn from(state: ModifiersState) -> Self {
+        Modifiers {
+            shift: state.shift(),
+            ctrl: state.ctrl(),
+            alt: state.alt(),
+            logo: state.logo(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Binding {
+    pub input: Input,
+    #[serde(default)]
+    pub modifiers: Modifiers,
+}
+
+impl Binding {
+    pub fn new(input: Input) -> Self {
+        Binding {
+            input,
+            modifiers: Modifiers::default(),
+        }
+    }
+}
+
+impl std::fmt::Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let m = &self.modifiers;
+        for (held, name) in [
+            (m.ctrl, "Ctrl"),
+            (m.alt, "Alt"),
+            (m.shift, "Shift"),
+            (m.logo, "Logo"),
+        ] {
+            if held {
+                write!(f, "{}+", name)?;
+            }
+        }
+        write!(f, "{}", self.input)
+    }
+}
+
+/// Keys and buttons bound to each action, saved as RON. Actions missing from
+/// a file keep their default bindings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Bindings {
+    map: BTreeMap<Action, Vec<Binding>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use VirtualKeyCode::*;
+
+        let key = |key| Binding::new(Input::Key(key));
+        let map = [
+            (Action::MoveForward, vec![key(W), key(Up)]),
+            (Action::MoveBackward, vec![key(S), key(Down)]),
+            (Action::MoveLeft, vec![key(A), key(Left)]),
+            (Action::MoveRight, vec![key(D), key(Right)]),
+            (Action::MoveUp, vec![key(Q)]),
+            (Action::MoveDown, vec![key(Z)]),
+            (
+                Action::Look,
+                vec![Binding::new(Input::Mouse(MouseButton::Left))],
+            ),
+            (Action::Pan, vec![key(LShift), key(RShift)]),
+            (Action::ToggleRotation, vec![key(R)]),
+            (Action::CaptureFrame, vec![key(P)]),
+            (Action::ToggleRecording, vec![key(V)]),
+            (Action::ToggleCameraMode, vec![key(C)]),
+            (Action::AddKeyframe, vec![key(K)]),
+            (Action::ToggleCameraPath, vec![key(L)]),
+            (Action::TogglePause, vec![key(Space)]),
+            (Action::SingleStep, vec![key(Period)]),
+        ];
+        Bindings {
+            map: map.into_iter().collect(),
+        }
+    }
+}
+
+impl Bindings {
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let file: Bindings = ron::from_str(text)?;
+        let mut bindings = Bindings::default();
+        bindings.map.extend(file.map);
+        Ok(bindings)
+    }
+
+    /// Load bindings from `res/`, or from an absolute path on native.
+    pub async fn load(file_name: &str) -> anyhow::Result<Self> {
+        let text = load_string(file_name).await?;
+        Self::parse(&text).map_err(|e| anyhow::anyhow!("{}: {}", file_name, e))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read<P: AsRef<std::path::Path>>(path: P) -> anyhow::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> anyhow::Result<()> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.map.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Replace the bindings of `action` with a single one, taking it from
+    /// any other action it was bound to.
+    pub fn set(&mut self, action: Action, binding: Binding) {
+        for bindings in self.map.values_mut() {
+            bindings.retain(|b| *b != binding);
+        }
+        self.map.insert(action, vec![binding]);
+    }
+
+    pub fn add(&mut self, action: Action, binding: Binding) {
+        let bindings = self.map.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn clear(&mut self, action: Action) {
+        self.map.insert(action, Vec::new());
+    }
+
+    /// The action `input` triggers while `modifiers` are held. When several
+    /// bindings match, the one needing the most modifiers wins.
+    pub fn action(&self, input: Input, modifiers: ModifiersState) -> Option<Action> {
+        self.map
+            .iter()
+            .filter(|(action, _)| action.is_available())
+            .flat_map(|(action, bindings)| bindings.iter().map(move |b| (*action, b)))
+            .filter(|(_, b)| b.input == input && b.modifiers.held_in(modifiers))
+            .max_by_key(|(_, b)| b.modifiers.count())
+            .map(|(action, _)| action)
+    }
+
+    /// Held actions bound to `input`, whatever the modifiers, so releasing a
+    /// key always ends what pressing it started.
+    pub fn held_actions(&self, input: Input) -> impl Iterator<Item = Action> + '_ {
+        self.map
+            .iter()
+            .filter(move |(action, bindings)| {
+                action.is_held() && bindings.iter().any(|b| b.input == input)
+            })
+            .map(|(action, _)| *action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn action_priority() {
+        let mut bindings = Bindings::default();
+        let k = Input::Key(VirtualKeyCode::K);
+        let ctrl_k = Binding {
+            input: k,
+            modifiers: Modifiers::from(ModifiersState::CTRL),
+        };
+        bindings.add(Action::ToggleCameraPath, ctrl_k);
+        assert_eq!(
+            bindings.action(k, ModifiersState::empty()),
+            Some(Action::AddKeyframe)
+        );
+        // Unneeded modifiers don't stop a binding, but the one needing the
+        // most held modifiers wins.
+        assert_eq!(
+            bindings.action(k, ModifiersState::SHIFT),
+            Some(Action::AddKeyframe)
+        );
+        assert_eq!(
+            bindings.action(k, ModifiersState::CTRL | ModifiersState::SHIFT),
+            Some(Action::ToggleCameraPath)
+        );
+        assert_eq!(
+            bindings.action(Input::Key(VirtualKeyCode::J), ModifiersState::empty()),
+            None
+        );
+    }
+}
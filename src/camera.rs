@@ -1,9 +1,6 @@
-use cgmath::{InnerSpace, SquareMatrix};
+use cgmath::{EuclideanSpace, InnerSpace, SquareMatrix};
 use wgpu::util::DeviceExt;
-use winit::{
-    dpi::PhysicalPosition,
-    event::{ElementState, MouseScrollDelta, VirtualKeyCode},
-};
+use winit::{dpi::PhysicalPosition, event::MouseScrollDelta};
 
 #[rustfmt::skip]
 pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
@@ -14,6 +11,13 @@ pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
 );
 const SAFE_FRAC_PI_2: f32 = std::f32::consts::FRAC_PI_2 - 0.0001;
 
+/// Closest an orbit camera gets to its target.
+const MIN_ORBIT_DISTANCE: f32 = 0.5;
+/// Orbit distance used when the view doesn't face the origin.
+const DEFAULT_ORBIT_DISTANCE: f32 = 10.0;
+/// Target movement per pixel of mouse motion, per unit of orbit distance.
+const PAN_SPEED: f32 = 0.002;
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct CameraUniform {
@@ -22,6 +26,8 @@ pub struct CameraUniform {
     proj: [[f32; 4]; 4],
     proj_inv: [[f32; 4]; 4],
     view: [[f32; 4]; 4],
+    far_depth: f32,
+    _padding: [f32; 3],
 }
 
 impl CameraUniform {
@@ -32,10 +38,39 @@ impl CameraUniform {
             proj: cgmath::Matrix4::identity().into(),
             proj_inv: cgmath::Matrix4::identity().into(),
             view: cgmath::Matrix4::identity().into(),
+            far_depth: 1.0,
+            _padding: [0.0; 3],
         }
     }
 }
 
+/// How `CameraController` moves the camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Fly with the move actions and look around with the mouse.
+    Fly,
+    /// Drag to circle a target point, scroll to zoom toward it and drag with
+    /// the pan action held to pan it.
+    Orbit,
+}
+
+/// Direction of a fly camera move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Move {
+    Forward,
+    Backward,
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Orbit {
+    target: cgmath::Point3<f32>,
+    distance: f32,
+}
+
 #[derive(Default)]
 pub struct CameraController {
     amount_left: f32,
@@ -49,6 +84,9 @@ pub struct CameraController {
     scroll: f32,
     speed: f32,
     sensitivity: f32,
+    /// Set in orbit mode.
+    orbit: Option<Orbit>,
+    pan: bool,
 }
 
 impl CameraController {
@@ -60,35 +98,68 @@ impl CameraController {
         }
     }
 
-    pub fn process_keyboard(&mut self, key: VirtualKeyCode, state: ElementState) -> bool {
-        let amount = if state == ElementState::Pressed {
-            1.0
-        } else {
-            0.0
-        };
-        match key {
-            VirtualKeyCode::W | VirtualKeyCode::Up => {
-                self.amount_forward = amount;
-            }
-            VirtualKeyCode::A | VirtualKeyCode::Left => {
-                self.amount_left = amount;
-            }
-            VirtualKeyCode::S | VirtualKeyCode::Down => {
-                self.amount_backward = amount;
-            }
-            VirtualKeyCode::D | VirtualKeyCode::Right => {
-                self.amount_right = amount;
-            }
-            VirtualKeyCode::Q => {
-                self.amount_up = amount;
-            }
-            VirtualKeyCode::Z => {
-                self.amount_down = amount;
+    /// Start or stop moving in `direction`.
+    pub fn process_move(&mut self, direction: Move, pressed: bool) {
+        let amount = if pressed { 1.0 } else { 0.0 };
+        match direction {
+            Move::Forward => self.amount_forward = amount,
+            Move::Backward => self.amount_backward = amount,
+            Move::Left => self.amount_left = amount,
+            Move::Right => self.amount_right = amount,
+            Move::Up => self.amount_up = amount,
+            Move::Down => self.amount_down = amount,
+        }
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        match self.orbit {
+            Some(_) => CameraMode::Orbit,
+            None => CameraMode::Fly,
+        }
+    }
+
+    /// Switch modes without moving the camera. An orbit starts around the
+    /// point of the view ray closest to the origin.
+    pub fn set_mode(&mut self, mode: CameraMode, camera: &Camera) {
+        if mode == self.mode() {
+            return;
+        }
+        self.orbit = match mode {
+            CameraMode::Fly => None,
+            CameraMode::Orbit => {
+                let forward = camera.forward();
+                let distance = (-camera.position.to_vec()).dot(forward);
+                let distance = if distance < MIN_ORBIT_DISTANCE {
+                    DEFAULT_ORBIT_DISTANCE
+                } else {
+                    distance
+                };
+                Some(Orbit {
+                    target: camera.position + forward * distance,
+                    distance,
+                })
             }
-            _ => return false,
+        };
+    }
+
+    pub fn toggle_mode(&mut self, camera: &Camera) {
+        let mode = match self.mode() {
+            CameraMode::Fly => CameraMode::Orbit,
+            CameraMode::Orbit => CameraMode::Fly,
+        };
+        self.set_mode(mode, camera);
+    }
+
+    /// Orbit around `target` from the current distance.
+    pub fn set_orbit_target<P: Into<cgmath::Point3<f32>>>(&mut self, target: P) {
+        if let Some(orbit) = self.orbit.as_mut() {
+            orbit.target = target.into();
         }
+    }
 
-        true
+    /// Turn orbit drags into pans while set.
+    pub fn set_pan(&mut self, pan: bool) {
+        self.pan = pan;
     }
 
     pub fn process_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
@@ -104,6 +175,10 @@ impl CameraController {
     }
 
     pub fn update_camera(&mut self, camera: &mut Camera, dt: instant::Duration) {
+        if self.orbit.is_some() {
+            self.update_orbit(camera, dt);
+            return;
+        }
         let dt = dt.as_secs_f32();
 
         let (yaw_sin, yaw_cos) = camera.yaw.0.sin_cos();
@@ -126,19 +201,57 @@ impl CameraController {
         self.rotate_horizontal = 0.0;
         self.rotate_vertical = 0.0;
 
-        if camera.pitch < -cgmath::Rad(SAFE_FRAC_PI_2) {
-            camera.pitch = -cgmath::Rad(SAFE_FRAC_PI_2);
-        } else if camera.pitch > cgmath::Rad(SAFE_FRAC_PI_2) {
-            camera.pitch = cgmath::Rad(SAFE_FRAC_PI_2);
+        camera.clamp_pitch();
+    }
+
+    fn update_orbit(&mut self, camera: &mut Camera, dt: instant::Duration) {
+        let dt = dt.as_secs_f32();
+        let Some(orbit) = self.orbit.as_mut() else {
+            return;
+        };
+
+        if self.pan {
+            let forward = camera.forward();
+            let right = forward.cross(cgmath::Vector3::unit_y()).normalize();
+            let up = right.cross(forward);
+            let scale = orbit.distance * PAN_SPEED;
+            orbit.target += (up * self.rotate_vertical - right * self.rotate_horizontal) * scale;
+        } else {
+            camera.yaw += cgmath::Rad(self.rotate_horizontal) * self.sensitivity * dt;
+            camera.pitch += cgmath::Rad(-self.rotate_vertical) * self.sensitivity * dt;
+            camera.clamp_pitch();
         }
+        self.rotate_horizontal = 0.0;
+        self.rotate_vertical = 0.0;
+
+        // Zoom by a fraction of the distance, so it slows near the target.
+        let zoom = (-self.scroll * self.sensitivity * dt * 0.25).exp();
+        orbit.distance = (orbit.distance * zoom).max(MIN_ORBIT_DISTANCE);
+        self.scroll = 0.0;
+
+        camera.position = orbit.target - camera.forward() * orbit.distance;
     }
 }
 
+/// How `Projection` maps view space to clip space.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectionKind {
+    #[default]
+    Perspective,
+    /// Perspective without a far plane, with depth running from 1 at the
+    /// near plane to 0 at infinity for even precision.
+    InfiniteReverseZ,
+    /// Parallel projection, `ortho_height` world units tall.
+    Orthographic,
+}
+
 pub struct Projection {
+    kind: ProjectionKind,
     aspect: f32,
     fovy: cgmath::Rad<f32>,
     znear: f32,
     zfar: f32,
+    ortho_height: f32,
 }
 
 impl Projection {
@@ -150,10 +263,12 @@ impl Projection {
         zfar: f32,
     ) -> Self {
         Projection {
+            kind: ProjectionKind::default(),
             aspect: width as f32 / height as f32,
             fovy: fovy.into(),
             znear,
             zfar,
+            ortho_height: 10.0,
         }
     }
 
@@ -161,8 +276,51 @@ impl Projection {
         self.aspect = width as f32 / height as f32;
     }
 
+    pub fn set_fovy<F: Into<cgmath::Rad<f32>>>(&mut self, fovy: F) {
+        self.fovy = fovy.into();
+    }
+
+    pub fn kind(&self) -> ProjectionKind {
+        self.kind
+    }
+
+    pub fn set_kind(&mut self, kind: ProjectionKind) {
+        self.kind = kind;
+    }
+
+    pub fn set_ortho_height(&mut self, height: f32) {
+        self.ortho_height = height;
+    }
+
+    /// Whether depth decreases with distance.
+    pub fn is_reverse_z(&self) -> bool {
+        self.kind == ProjectionKind::InfiniteReverseZ
+    }
+
     pub fn calc_matrix(&self) -> cgmath::Matrix4<f32> {
-        OPENGL_TO_WGPU_MATRIX * cgmath::perspective(self.fovy, self.aspect, self.znear, self.zfar)
+        match self.kind {
+            ProjectionKind::Perspective => {
+                OPENGL_TO_WGPU_MATRIX
+                    * cgmath::perspective(self.fovy, self.aspect, self.znear, self.zfar)
+            }
+            ProjectionKind::InfiniteReverseZ => {
+                let f = 1.0 / (self.fovy.0 / 2.0).tan();
+                #[rustfmt::skip]
+                let matrix = cgmath::Matrix4::new(
+                    f / self.aspect, 0.0, 0.0, 0.0,
+                    0.0, f, 0.0, 0.0,
+                    0.0, 0.0, 0.0, -1.0,
+                    0.0, 0.0, self.znear, 0.0,
+                );
+                matrix
+            }
+            ProjectionKind::Orthographic => {
+                let top = self.ortho_height / 2.0;
+                let right = top * self.aspect;
+                OPENGL_TO_WGPU_MATRIX
+                    * cgmath::ortho(-right, right, -top, top, self.znear, self.zfar)
+            }
+        }
     }
 }
 
@@ -229,13 +387,44 @@ impl Camera {
         }
     }
 
+    pub fn set_pose<
+        V: Into<cgmath::Point3<f32>>,
+        Y: Into<cgmath::Rad<f32>>,
+        P: Into<cgmath::Rad<f32>>,
+    >(
+        &mut self,
+        position: V,
+        yaw: Y,
+        pitch: P,
+    ) {
+        self.position = position.into();
+        self.yaw = yaw.into();
+        self.pitch = pitch.into();
+    }
+
+    pub fn yaw(&self) -> cgmath::Rad<f32> {
+        self.yaw
+    }
+
+    pub fn pitch(&self) -> cgmath::Rad<f32> {
+        self.pitch
+    }
+
+    /// Unit vector in the direction the camera looks.
+    pub fn forward(&self) -> cgmath::Vector3<f32> {
+        cgmath::Vector3::new(self.yaw.0.cos(), self.pitch.0.sin(), self.yaw.0.sin()).normalize()
+    }
+
+    fn clamp_pitch(&mut self) {
+        if self.pitch < -cgmath::Rad(SAFE_FRAC_PI_2) {
+            self.pitch = -cgmath::Rad(SAFE_FRAC_PI_2);
+        } else if self.pitch > cgmath::Rad(SAFE_FRAC_PI_2) {
+            self.pitch = cgmath::Rad(SAFE_FRAC_PI_2);
+        }
+    }
+
     fn calc_matrix(&self) -> cgmath::Matrix4<f32> {
-        cgmath::Matrix4::look_to_rh(
-            self.position,
-            cgmath::Vector3::new(self.yaw.0.cos(), self.pitch.0.sin(), self.yaw.0.sin())
-                .normalize(),
-            cgmath::Vector3::unit_y(),
-        )
+        cgmath::Matrix4::look_to_rh(self.position, self.forward(), cgmath::Vector3::unit_y())
     }
 
     pub fn update_view_proj(&mut self, projection: &Projection) {
@@ -245,5 +434,6 @@ impl Camera {
         use cgmath::Transform;
         self.uniform.proj_inv = projection.calc_matrix().inverse_transform().unwrap().into();
         self.uniform.view_proj = (projection.calc_matrix() * self.calc_matrix()).into();
+        self.uniform.far_depth = crate::texture::far_depth(projection.is_reverse_z());
     }
 }
@@ -0,0 +1,269 @@
+use serde::{Deserialize, Serialize};
+
+use crate::resources::load_string;
+
+/// Easing curve of the move into a keyframe, from `easer::functions`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Easing {
+    #[default]
+    Linear,
+    Sine,
+    Quad,
+    Cubic,
+    Quart,
+    Quint,
+    Expo,
+    Circ,
+    Back,
+    Elastic,
+    Bounce,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EaseMode {
+    In,
+    Out,
+    #[default]
+    InOut,
+}
+
+impl Easing {
+    pub const ALL: [Easing; 11] = [
+        Easing::Linear,
+        Easing::Sine,
+        Easing::Quad,
+        Easing::Cubic,
+        Easing::Quart,
+        Easing::Quint,
+        Easing::Expo,
+        Easing::Circ,
+        Easing::Back,
+        Easing::Elastic,
+        Easing::Bounce,
+    ];
+
+    /// Map `t` in `0.0..=1.0` onto the curve.
+    pub fn apply(self, mode: EaseMode, t: f32) -> f32 {
+        use easer::functions::{
+            Back, Bounce, Circ, Cubic, Elastic, Expo, Linear, Quad, Quart, Quint, Sine,
+        };
+
+        fn ease<E: easer::functions::Easing<f32>>(mode: EaseMode, t: f32) -> f32 {
+            match mode {
+                EaseMode::In => E::ease_in(t, 0.0, 1.0, 1.0),
+                EaseMode::Out => E::ease_out(t, 0.0, 1.0, 1.0),
+                EaseMode::InOut => E::ease_in_out(t, 0.0, 1.0, 1.0),
+            }
+        }
+
+        match self {
+            Self::Linear => ease::<Linear>(mode, t),
+            Self::Sine => ease::<Sine>(mode, t),
+            Self::Quad => ease::<Quad>(mode, t),
+            Self::Cubic => ease::<Cubic>(mode, t),
+            Self::Quart => ease::<Quart>(mode, t),
+            Self::Quint => ease::<Quint>(mode, t),
+            Self::Expo => ease::<Expo>(mode, t),
+            Self::Circ => ease::<Circ>(mode, t),
+            Self::Back => ease::<Back>(mode, t),
+            Self::Elastic => ease::<Elastic>(mode, t),
+            Self::Bounce => ease::<Bounce>(mode, t),
+        }
+    }
+}
+
+/// What happens when playback reaches the last keyframe.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Repeat {
+    #[default]
+    Once,
+    /// Jump back to the first keyframe.
+    Loop,
+    /// Play backwards to the first keyframe, then forwards again.
+    PingPong,
+}
+
+impl Repeat {
+    /// Map `time` onto `0.0..=total`.
+    pub fn wrap(self, time: f32, total: f32) -> f32 {
+        if total <= 0.0 {
+            return 0.0;
+        }
+        match self {
+            Repeat::Once => time.clamp(0.0, total),
+            Repeat::Loop => time.rem_euclid(total),
+            Repeat::PingPong => total - (time.rem_euclid(2.0 * total) - total).abs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    pub position: [f32; 3],
+    /// Degrees.
+    pub yaw: f32,
+    /// Degrees.
+    pub pitch: f32,
+    /// Seconds spent moving here from the previous keyframe.
+    #[serde(default = "default_duration")]
+    pub duration: f32,
+    #[serde(default)]
+    pub easing: Easing,
+    #[serde(default)]
+    pub mode: EaseMode,
+}
+
+fn default_duration() -> f32 {
+    2.0
+}
+
+/// A camera pose sampled from a path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pose {
+    pub position: cgmath::Point3<f32>,
+    pub yaw: cgmath::Deg<f32>,
+    pub pitch: cgmath::Deg<f32>,
+}
+
+/// Keyframed camera fly-through, saved as RON.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CameraPath {
+    pub keyframes: Vec<Keyframe>,
+    #[serde(default)]
+    pub repeat: Repeat,
+}
+
+impl CameraPath {
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        Ok(ron::from_str(text)?)
+    }
+
+    /// Load a path from `res/`, or from an absolute path on native.
+    pub async fn load(file_name: &str) -> anyhow::Result<Self> {
+        let text = load_string(file_name).await?;
+        Self::parse(&text).map_err(|e| anyhow::anyhow!("{}: {}", file_name, e))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read<P: AsRef<std::path::Path>>(path: P) -> anyhow::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> anyhow::Result<()> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Append a keyframe at the given camera pose.
+    pub fn push(&mut self, pose: Pose, duration: f32, easing: Easing) {
+        self.keyframes.push(Keyframe {
+            position: pose.position.into(),
+            yaw: pose.yaw.0,
+            pitch: pose.pitch.0,
+            duration,
+            easing,
+            mode: EaseMode::default(),
+        });
+    }
+
+    /// Seconds from the first keyframe to the last.
+    pub fn duration(&self) -> f32 {
+        self.keyframes.iter().skip(1).map(|k| k.duration).sum()
+    }
+
+    /// Whether playback ends at `time` instead of repeating.
+    pub fn is_finished(&self, time: f32) -> bool {
+        self.repeat == Repeat::Once && time >= self.duration()
+    }
+
+    /// The camera pose `time` seconds into playback.
+    pub fn sample(&self, time: f32) -> Option<Pose> {
+        let first = self.keyframes.first()?;
+        let total = self.duration();
+        let mut time = self.repeat.wrap(time, total);
+
+        let mut from = first;
+        for to in &self.keyframes[1..] {
+            if time <= to.duration {
+                let t = if to.duration > 0.0 {
+                    to.easing.apply(to.mode, time / to.duration)
+                } else {
+                    1.0
+                };
+                return Some(lerp(from, to, t));
+            }
+            time -= to.duration;
+            from = to;
+        }
+        Some(lerp(from, from, 0.0))
+    }
+}
+
+fn lerp(from: &Keyframe, to: &Keyframe, t: f32) -> Pose {
+    let mix = |a: f32, b: f32| a + (b - a) * t;
+    let [x0, y0, z0] = from.position;
+    let [x1, y1, z1] = to.position;
+    Pose {
+        position: cgmath::Point3::new(mix(x0, x1), mix(y0, y1), mix(z0, z1)),
+        yaw: cgmath::Deg(mix(from.yaw, to.yaw)),
+        pitch: cgmath::Deg(mix(from.pitch, to.pitch)),
+    }
+}
+
+/// Playback position along a `CameraPath`.
+#[derive(Debug, Default)]
+pub struct PathPlayer {
+    pub path: CameraPath,
+    time: f32,
+    playing: bool,
+}
+
+impl PathPlayer {
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Play from the first keyframe.
+    pub fn play(&mut self) {
+        self.time = 0.0;
+        self.playing = self.path.keyframes.len() > 1;
+    }
+
+    pub fn stop(&mut self) {
+        self.playing = false;
+    }
+
+    /// Advance playback, returning the camera pose while playing.
+    pub fn advance(&mut self, dt: f32) -> Option<Pose> {
+        if !self.playing {
+            return None;
+        }
+        self.time += dt;
+        if self.path.is_finished(self.time) {
+            self.playing = false;
+        }
+        self.path.sample(self.time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeat_wrap() {
+        assert_eq!(Repeat::Once.wrap(-1.0, 2.0), 0.0);
+        assert_eq!(Repeat::Once.wrap(1.5, 2.0), 1.5);
+        assert_eq!(Repeat::Once.wrap(3.0, 2.0), 2.0);
+        assert_eq!(Repeat::Loop.wrap(5.0, 2.0), 1.0);
+        assert_eq!(Repeat::Loop.wrap(-0.5, 2.0), 1.5);
+        assert_eq!(Repeat::PingPong.wrap(1.5, 2.0), 1.5);
+        assert_eq!(Repeat::PingPong.wrap(2.5, 2.0), 1.5);
+        assert_eq!(Repeat::PingPong.wrap(4.5, 2.0), 0.5);
+        for repeat in [Repeat::Once, Repeat::Loop, Repeat::PingPong] {
+            assert_eq!(repeat.wrap(1.0, 0.0), 0.0);
+        }
+    }
+}
@@ -0,0 +1,178 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+
+/// Tracks which rendered frames should be written to disk as PNG files.
+/// Native only, as the web has no file system to write to.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FrameCapture {
+    dir: PathBuf,
+    pending: Vec<PathBuf>,
+    shots: u32,
+    sequence: Option<Sequence>,
+}
+
+/// A run of numbered frames written to their own directory.
+#[cfg(not(target_arch = "wasm32"))]
+struct Sequence {
+    dir: PathBuf,
+    frame: u32,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FrameCapture {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        FrameCapture {
+            dir: dir.into(),
+            pending: Vec::new(),
+            shots: 0,
+            sequence: None,
+        }
+    }
+
+    /// Capture the next rendered frame to `path`.
+    pub fn capture_to<P: Into<PathBuf>>(&mut self, path: P) {
+        self.pending.push(path.into());
+    }
+
+    /// Capture the next rendered frame to a numbered file in the capture directory.
+    pub fn capture_frame(&mut self) {
+        self.shots += 1;
+        let path = self.dir.join(format!("frame-{:04}.png", self.shots));
+        self.capture_to(path);
+    }
+
+    /// Write every rendered frame to `dir` as `00000.png`, `00001.png`, ...
+    pub fn start_sequence<P: Into<PathBuf>>(&mut self, dir: P) {
+        let dir = dir.into();
+        log::info!("Recording frame sequence to {:?}", dir);
+        self.sequence = Some(Sequence { dir, frame: 0 });
+    }
+
+    pub fn stop_sequence(&mut self) {
+        if let Some(sequence) = self.sequence.take() {
+            log::info!("Recorded {} frames to {:?}", sequence.frame, sequence.dir);
+        }
+    }
+
+    /// Start a sequence in a fresh numbered subdirectory, or stop the current one.
+    pub fn toggle_sequence(&mut self) {
+        if self.sequence.is_some() {
+            self.stop_sequence();
+        } else {
+            let mut n = 1;
+            while self.dir.join(format!("sequence-{:03}", n)).exists() {
+                n += 1;
+            }
+            self.start_sequence(self.dir.join(format!("sequence-{:03}", n)));
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.sequence.is_some()
+    }
+
+    /// Whether the frame being rendered needs to be read back.
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty() || self.sequence.is_some()
+    }
+
+    /// Write `image` to every destination requested for the current frame.
+    pub fn save(&mut self, image: &image::RgbaImage) {
+        let mut paths = std::mem::take(&mut self.pending);
+        if let Some(sequence) = self.sequence.as_mut() {
+            paths.push(sequence.dir.join(format!("{:05}.png", sequence.frame)));
+            sequence.frame += 1;
+        }
+        for path in paths {
+            if let Err(e) = save_png(image, &path) {
+                log::error!("Failed to write {:?}: {}", path, e);
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_png(image: &image::RgbaImage, path: &Path) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    image.save_with_format(path, image::ImageFormat::Png)?;
+    Ok(())
+}
+
+/// Copy a rendered color texture back to the CPU. The texture needs
+/// `COPY_SRC` usage and an 8-bit RGBA or BGRA format.
+pub fn read_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> anyhow::Result<image::RgbaImage> {
+    let (width, height) = (texture.width(), texture.height());
+    let bgra = match texture.format() {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+        format => anyhow::bail!("Can't capture texture format {:?}", format),
+    };
+
+    // Rows of a texture copy must be padded to a multiple of 256 bytes.
+    let unpadded_bytes_per_row = 4 * width;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Capture"),
+        size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Capture"),
+    });
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            aspect: wgpu::TextureAspect::All,
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        sender.send(result).ok();
+    });
+    // Blocks on native. On the web the callback only runs from the event loop,
+    // so the receiver comes up empty instead of deadlocking.
+    device.poll(wgpu::Maintain::Wait);
+    receiver.try_recv()??;
+
+    let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+    {
+        let data = slice.get_mapped_range();
+        for row in data.chunks(padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+        }
+    }
+    buffer.unmap();
+
+    if bgra {
+        for pixel in pixels.chunks_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+
+    image::RgbaImage::from_raw(width, height, pixels)
+        .ok_or_else(|| anyhow::anyhow!("Captured buffer doesn't match texture size"))
+}
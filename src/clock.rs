@@ -0,0 +1,108 @@
+use instant::Duration;
+
+/// Simulation rate used by `RenderView`.
+pub const DEFAULT_STEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+/// Steps run for one frame at most, so a long stall doesn't snowball.
+const MAX_STEPS_PER_FRAME: u32 = 8;
+
+/// Turns frame times into fixed simulation steps, so animation runs at the
+/// same speed whatever the frame rate.
+#[derive(Debug)]
+pub struct SimClock {
+    step: Duration,
+    /// Scaled time not yet simulated, less than one step after `advance`.
+    accumulator: Duration,
+    steps: u64,
+    /// Simulated seconds per wall-clock second.
+    pub scale: f32,
+    paused: bool,
+    /// Steps requested while paused.
+    pending: u32,
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        Self::new(DEFAULT_STEP)
+    }
+}
+
+impl SimClock {
+    pub fn new(step: Duration) -> Self {
+        SimClock {
+            step,
+            accumulator: Duration::ZERO,
+            steps: 0,
+            scale: 1.0,
+            paused: false,
+            pending: 0,
+        }
+    }
+
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    /// Simulated time so far.
+    pub fn time(&self) -> Duration {
+        Duration::from_nanos(self.step.as_nanos() as u64 * self.steps)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.pending = 0;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.set_paused(!self.paused);
+    }
+
+    /// Run a single step on the next frame, pausing first if needed.
+    pub fn single_step(&mut self) {
+        if !self.paused {
+            self.set_paused(true);
+        }
+        self.pending += 1;
+    }
+
+    /// Take a frame time, returning how many fixed steps to simulate.
+    pub fn advance(&mut self, dt: Duration) -> u32 {
+        let steps = if self.paused {
+            std::mem::take(&mut self.pending)
+        } else {
+            self.accumulator += dt.mul_f32(self.scale.max(0.0));
+            let due = (self.accumulator.as_nanos() / self.step.as_nanos()) as u32;
+            self.accumulator -= self.step * due;
+            if due > MAX_STEPS_PER_FRAME {
+                self.accumulator = Duration::ZERO;
+            }
+            due.min(MAX_STEPS_PER_FRAME)
+        };
+        self.steps += steps as u64;
+        steps
+    }
+
+    /// How far rendering is between the last two steps, in `0.0..1.0`.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.step.as_secs_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_clamp() {
+        let mut clock = SimClock::default();
+        assert_eq!(clock.advance(DEFAULT_STEP * 3 + DEFAULT_STEP / 2), 3);
+        assert!((clock.alpha() - 0.5).abs() < 1e-6);
+        // A stall runs the most steps allowed and drops the rest.
+        assert_eq!(clock.advance(Duration::from_secs(1)), MAX_STEPS_PER_FRAME);
+        assert_eq!(clock.alpha(), 0.0);
+        assert_eq!(clock.time(), DEFAULT_STEP * (3 + MAX_STEPS_PER_FRAME));
+    }
+}
@@ -0,0 +1,80 @@
+use wgpu::util::DeviceExt;
+
+use crate::mipmap;
+
+/// Format of cube textures rendered by face passes, keeping HDR range.
+pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct FaceUniform {
+    index: u32,
+    size: f32,
+}
+
+/// Compile `source` after `cube_face.wgsl`, which provides the `Face`
+/// uniform struct, `vs_face`, `face_direction` and `face_clip`.
+pub fn shader(device: &wgpu::Device, label: &str, source: &str) -> wgpu::ShaderModule {
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(
+            format!("{}\n{}", include_str!("cube_face.wgsl"), source).into(),
+        ),
+    })
+}
+
+/// A cube texture of `size` pixels to render the first mip level of each
+/// layer of, then fill the rest with `MipmapGenerator::generate`.
+pub fn create_target(device: &wgpu::Device, size: u32, label: &str) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        size: wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 6,
+        },
+        mip_level_count: mipmap::level_count(size, size),
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: FORMAT,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        label: Some(label),
+        view_formats: &[],
+    })
+}
+
+/// View of the first mip level of layer `index` of `cube` to render into.
+pub fn layer_view(cube: &wgpu::Texture, index: u32) -> wgpu::TextureView {
+    cube.create_view(&wgpu::TextureViewDescriptor {
+        label: Some("Cube face"),
+        dimension: Some(wgpu::TextureViewDimension::D2),
+        mip_level_count: Some(1),
+        base_array_layer: index,
+        array_layer_count: Some(1),
+        ..Default::default()
+    })
+}
+
+/// The `Face` uniform for layer `index` of a cube of `size` pixels.
+pub fn uniform_buffer(device: &wgpu::Device, index: u32, size: u32) -> wgpu::Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Cube face"),
+        contents: bytemuck::cast_slice(&[FaceUniform {
+            index,
+            size: size as f32,
+        }]),
+        usage: wgpu::BufferUsages::UNIFORM,
+    })
+}
+
+pub fn uniform_layout_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
@@ -0,0 +1,48 @@
+// Shared by the passes that render a cube texture one layer at a time.
+
+struct Face {
+    index: u32,
+    size: f32,
+};
+
+@vertex
+fn vs_face(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    // One large triangle covering the face
+    let tmp1 = i32(vertex_index) / 2;
+    let tmp2 = i32(vertex_index) & 1;
+    return vec4<f32>(f32(tmp1) * 4.0 - 1.0, f32(tmp2) * 4.0 - 1.0, 0.0, 1.0);
+}
+
+// Direction through `uv` (y down) of cube layer `index`, in the layer order
+// +X, -X, +Y, -Y, +Z, -Z used when sampling a cube texture.
+fn face_direction(index: u32, uv: vec2<f32>) -> vec3<f32> {
+    let s = uv.x * 2.0 - 1.0;
+    let t = uv.y * 2.0 - 1.0;
+    var dir: vec3<f32>;
+    switch index {
+        case 0u: { dir = vec3<f32>(1.0, -t, -s); }
+        case 1u: { dir = vec3<f32>(-1.0, -t, s); }
+        case 2u: { dir = vec3<f32>(s, 1.0, t); }
+        case 3u: { dir = vec3<f32>(s, -1.0, -t); }
+        case 4u: { dir = vec3<f32>(s, -t, 1.0); }
+        default: { dir = vec3<f32>(-s, -t, -1.0); }
+    }
+    return normalize(dir);
+}
+
+// Clip position of `dir` on cube layer `index`, the inverse of
+// `face_direction`. Directions behind the face get a negative w.
+fn face_clip(index: u32, dir: vec3<f32>) -> vec4<f32> {
+    // s and t scaled by the distance along the face's axis, then that distance
+    var p: vec3<f32>;
+    switch index {
+        case 0u: { p = vec3<f32>(-dir.z, -dir.y, dir.x); }
+        case 1u: { p = vec3<f32>(dir.z, -dir.y, -dir.x); }
+        case 2u: { p = vec3<f32>(dir.x, dir.z, dir.y); }
+        case 3u: { p = vec3<f32>(dir.x, -dir.z, -dir.y); }
+        case 4u: { p = vec3<f32>(dir.x, -dir.y, dir.z); }
+        default: { p = vec3<f32>(-dir.x, -dir.y, -dir.z); }
+    }
+    // t runs down the face, clip y up
+    return vec4<f32>(p.x, -p.y, 0.0, p.z);
+}
@@ -1,14 +1,63 @@
+use wgpu::util::DeviceExt;
+
+use crate::{
+    instance::{Instance, InstanceRaw},
+    material::ShapeMaterial,
+    mesh::Mesh,
+    object::Transform,
+    render_pass::TrackedRenderPass,
+    texture::{self, DEPTH_FORMAT},
+};
+
 #[derive(Debug)]
 pub struct DrawShape {
-    pub vertex_fn: &'static str,
-    pub fragment_fn: &'static str,
+    pub vertex_fn: String,
+    pub fragment_fn: String,
+    /// Vertices (or indices, for an indexed mesh) to draw.
     pub vertex_count: u32,
+    /// Vertex and index buffer contents. Without a mesh the vertex shader
+    /// generates the geometry from `vertex_index`.
+    pub mesh: Option<Mesh>,
+    /// Depth test against shapes already drawn. `Always` disables occlusion.
+    pub depth_compare: wgpu::CompareFunction,
+    /// Record depth, so shapes drawn later are tested against this one.
+    pub depth_write: bool,
+    pub transform: Transform,
+    /// Copies drawn in one call. Starts as a single untransformed instance.
+    pub instances: Vec<Instance>,
+}
+
+impl DrawShape {
+    pub fn new(vertex_fn: &str, fragment_fn: &str, vertex_count: u32) -> Self {
+        DrawShape {
+            vertex_fn: vertex_fn.to_string(),
+            fragment_fn: fragment_fn.to_string(),
+            vertex_count,
+            mesh: None,
+            depth_compare: wgpu::CompareFunction::Less,
+            depth_write: true,
+            transform: Transform::default(),
+            instances: vec![Instance::default()],
+        }
+    }
+
+    pub fn with_mesh(vertex_fn: &str, fragment_fn: &str, mesh: Mesh) -> Self {
+        let mut shape = Self::new(vertex_fn, fragment_fn, mesh.element_count());
+        shape.mesh = Some(mesh);
+        shape
+    }
 }
 
-#[derive(Debug)]
 pub struct DrawShapePipeline {
     pub pipeline: wgpu::RenderPipeline,
     pub shape: DrawShape,
+    /// Replaces the view's default material for this shape.
+    pub material: Option<ShapeMaterial>,
+    pub vertex_buffer: Option<wgpu::Buffer>,
+    pub index_buffer: Option<wgpu::Buffer>,
+    instance_buffer: wgpu::Buffer,
+    instance_capacity: usize,
+    instances_changed: bool,
 }
 
 impl DrawShapePipeline {
@@ -16,47 +65,190 @@ impl DrawShapePipeline {
         device: &wgpu::Device,
         config: &wgpu::SurfaceConfiguration,
         shape: DrawShape,
+        material: Option<ShapeMaterial>,
         shader: &wgpu::ShaderModule,
         pipeline_layout: &wgpu::PipelineLayout,
+        reverse_z: bool,
     ) -> Self {
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: None,
-            layout: Some(pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: shader,
-                entry_point: shape.vertex_fn,
-                buffers: &[],
-            },
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                // Requires `Features::DEPTH_CLIP_CONTROL`.
-                unclipped_depth: false,
-                // Anything but Fill requires `Features::NON_FILL_POLYGON_MODE`.
-                polygon_mode: wgpu::PolygonMode::Fill,
-                // Requires `Features::CONSERVATIVE_RASTERIZATION`.
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: shader,
-                entry_point: shape.fragment_fn,
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            multiview: None,
+        let vertex_buffer = shape.mesh.as_ref().map(|mesh| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("{} Vertex Buffer", shape.vertex_fn)),
+                contents: &mesh.vertices,
+                usage: wgpu::BufferUsages::VERTEX,
+            })
         });
+        let index_buffer = shape
+            .mesh
+            .as_ref()
+            .and_then(|mesh| mesh.indices.as_ref())
+            .map(|indices| {
+                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(&format!("{} Index Buffer", shape.vertex_fn)),
+                    contents: bytemuck::cast_slice(indices),
+                    usage: wgpu::BufferUsages::INDEX,
+                })
+            });
+        let instance_capacity = shape.instances.len().max(1);
+        let instance_buffer = create_instance_buffer(device, instance_capacity);
+        let blend = blend_state(material.as_ref());
+        let pipeline = create_pipeline(
+            device,
+            config,
+            &shape,
+            blend,
+            shader,
+            pipeline_layout,
+            reverse_z,
+        );
 
-        DrawShapePipeline { pipeline, shape }
+        DrawShapePipeline {
+            pipeline,
+            shape,
+            material,
+            vertex_buffer,
+            index_buffer,
+            instance_buffer,
+            instance_capacity,
+            instances_changed: true,
+        }
     }
+
+    /// Recreate the pipeline, e.g. for a projection with a different depth direction.
+    pub fn rebuild(
+        &mut self,
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        shader: &wgpu::ShaderModule,
+        pipeline_layout: &wgpu::PipelineLayout,
+        reverse_z: bool,
+    ) {
+        self.pipeline = create_pipeline(
+            device,
+            config,
+            &self.shape,
+            blend_state(self.material.as_ref()),
+            shader,
+            pipeline_layout,
+            reverse_z,
+        );
+    }
+
+    /// Instances to edit. They're uploaded by the next `write_instances`.
+    pub fn instances_mut(&mut self) -> &mut Vec<Instance> {
+        self.instances_changed = true;
+        &mut self.shape.instances
+    }
+
+    /// Upload changed instances, growing the buffer when needed.
+    pub fn write_instances(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if !self.instances_changed {
+            return;
+        }
+        self.instances_changed = false;
+
+        let instances = &self.shape.instances;
+        if instances.len() > self.instance_capacity {
+            self.instance_capacity = instances.len().next_power_of_two();
+            self.instance_buffer = create_instance_buffer(device, self.instance_capacity);
+        }
+        let data: Vec<InstanceRaw> = instances.iter().map(InstanceRaw::from).collect();
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&data));
+    }
+
+    /// Bind the shape's buffers and issue its draw call.
+    pub fn draw<'a>(&'a self, render_pass: &mut TrackedRenderPass<'a>) {
+        render_pass.set_pipeline(&self.pipeline);
+        let mut slot = 0;
+        if let Some(vertex_buffer) = &self.vertex_buffer {
+            render_pass.set_vertex_buffer(slot, vertex_buffer.slice(..));
+            slot += 1;
+        }
+        render_pass.set_vertex_buffer(slot, self.instance_buffer.slice(..));
+
+        let instances = 0..self.shape.instances.len() as u32;
+        match &self.index_buffer {
+            Some(index_buffer) => {
+                render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed(0..self.shape.vertex_count, 0, instances);
+            }
+            None => render_pass.draw(0..self.shape.vertex_count, instances),
+        }
+    }
+}
+
+fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Instance Buffer"),
+        size: (capacity * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// Shapes without their own material use the view's default, which blends
+/// by straight alpha.
+fn blend_state(material: Option<&ShapeMaterial>) -> wgpu::BlendState {
+    material.map_or(wgpu::BlendState::ALPHA_BLENDING, ShapeMaterial::blend_state)
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    shape: &DrawShape,
+    blend: wgpu::BlendState,
+    shader: &wgpu::ShaderModule,
+    pipeline_layout: &wgpu::PipelineLayout,
+    reverse_z: bool,
+) -> wgpu::RenderPipeline {
+    // The instance buffer follows the mesh, if there is one.
+    let buffers: Vec<_> = shape
+        .mesh
+        .iter()
+        .map(|mesh| mesh.layout())
+        .chain(std::iter::once(InstanceRaw::layout()))
+        .collect();
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: &shape.vertex_fn,
+            buffers: &buffers,
+        },
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            // Requires `Features::DEPTH_CLIP_CONTROL`.
+            unclipped_depth: false,
+            // Anything but Fill requires `Features::NON_FILL_POLYGON_MODE`.
+            polygon_mode: wgpu::PolygonMode::Fill,
+            // Requires `Features::CONSERVATIVE_RASTERIZATION`.
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: shape.depth_write,
+            depth_compare: texture::depth_compare(shape.depth_compare, reverse_z),
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: &shape.fragment_fn,
+            targets: &[Some(wgpu::ColorTargetState {
+                format: config.format,
+                blend: Some(blend),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+    })
 }
@@ -0,0 +1,20 @@
+// Projects an equirectangular panorama onto one cube face per pass.
+
+@group(0) @binding(0)
+var t_panorama: texture_2d<f32>;
+@group(0) @binding(1)
+var s_panorama: sampler;
+@group(0) @binding(2)
+var<uniform> face: Face;
+
+@fragment
+fn fs_face(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let dir = face_direction(face.index, position.xy / face.size);
+    let pi = 3.14159265;
+    // The middle of the panorama faces -Z, with +X to its right.
+    let uv = vec2<f32>(
+        0.5 + atan2(dir.x, -dir.z) / (2.0 * pi),
+        acos(clamp(dir.y, -1.0, 1.0)) / pi
+    );
+    return vec4<f32>(textureSampleLevel(t_panorama, s_panorama, uv, 0.0).rgb, 1.0);
+}
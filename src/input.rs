@@ -1,43 +1,70 @@
-use winit::event::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent};
+use serde::{Deserialize, Serialize};
+use winit::event::{
+    DeviceEvent, ElementState, KeyboardInput, ModifiersState, MouseScrollDelta, WindowEvent,
+};
 
-use crate::view::RenderView;
+use crate::{bindings::Input, view::RenderView};
 
-pub fn process_event(view: &mut RenderView, event: &WindowEvent) -> bool {
-    match event {
-        WindowEvent::KeyboardInput {
-            input:
-                KeyboardInput {
-                    state,
-                    virtual_keycode: Some(key),
-                    ..
-                },
-            ..
-        } => {
-            let is_pressed = *state == ElementState::Pressed;
-            match key {
-                VirtualKeyCode::R => {
-                    if is_pressed {
-                        view.keys.rotation = !view.keys.rotation;
-                    }
-                }
-                _ => return view.camera_controller.process_keyboard(*key, *state),
-            }
+/// The parts of window and device events that drive the view, in a form
+/// that can be recorded and replayed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+    Button { input: Input, pressed: bool },
+    Modifiers(ModifiersState),
+    Scroll(MouseScrollDelta),
+    MouseMotion(f64, f64),
+}
+
+impl InputEvent {
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        Some(match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => InputEvent::Button {
+                input: Input::Key(*key),
+                pressed: *state == ElementState::Pressed,
+            },
+            WindowEvent::MouseInput { button, state, .. } => InputEvent::Button {
+                input: Input::Mouse(*button),
+                pressed: *state == ElementState::Pressed,
+            },
+            WindowEvent::ModifiersChanged(modifiers) => InputEvent::Modifiers(*modifiers),
+            WindowEvent::MouseWheel { delta, .. } => InputEvent::Scroll(*delta),
+            _ => return None,
+        })
+    }
+
+    pub fn from_device_event(event: &DeviceEvent) -> Option<Self> {
+        match event {
+            DeviceEvent::MouseMotion { delta } => Some(InputEvent::MouseMotion(delta.0, delta.1)),
+            _ => None,
         }
+    }
+}
 
-        WindowEvent::MouseWheel { delta, .. } => {
+/// Apply an input to the view, returning whether it was used.
+pub fn process_input(view: &mut RenderView, event: &InputEvent) -> bool {
+    match event {
+        InputEvent::Button { input, pressed } => view.process_input(*input, *pressed),
+        InputEvent::Modifiers(modifiers) => {
+            view.set_modifiers(*modifiers);
+            true
+        }
+        InputEvent::Scroll(delta) => {
             view.camera_controller.process_scroll(delta);
+            true
         }
-
-        WindowEvent::MouseInput {
-            button: MouseButton::Left,
-            state,
-            ..
-        } => {
-            view.mouse_pressed = *state == ElementState::Pressed;
+        InputEvent::MouseMotion(dx, dy) => {
+            if view.mouse_pressed {
+                view.camera_controller.process_mouse(*dx, *dy);
+            }
+            true
         }
-
-        _ => return false,
     }
-
-    true
 }
@@ -0,0 +1,66 @@
+use serde::Deserialize;
+
+use crate::{mesh::Vertex, object::Transform};
+
+/// One copy of a shape, drawn in the same call as its siblings.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct Instance {
+    /// Placement relative to the shape's own transform.
+    pub transform: Transform,
+    /// Multiplied with the shape's color.
+    pub tint: [f32; 4],
+    /// Radians added to the noise-driven spin, so instances don't turn in lockstep.
+    pub phase: f32,
+}
+
+impl Default for Instance {
+    fn default() -> Self {
+        Instance {
+            transform: Transform::default(),
+            tint: [1.0; 4],
+            phase: 0.0,
+        }
+    }
+}
+
+/// Matches `InstanceInput` in shader.wgsl.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct InstanceRaw {
+    model: [[f32; 4]; 4],
+    tint: [f32; 4],
+    phase: f32,
+}
+
+impl From<&Instance> for InstanceRaw {
+    fn from(instance: &Instance) -> Self {
+        InstanceRaw {
+            model: instance.transform.to_matrix().into(),
+            tint: instance.tint,
+            phase: instance.phase,
+        }
+    }
+}
+
+impl Vertex for InstanceRaw {
+    // Locations after those of the mesh and model vertex types.
+    const ATTRIBUTES: &'static [wgpu::VertexAttribute] = &wgpu::vertex_attr_array![
+        5 => Float32x4,
+        6 => Float32x4,
+        7 => Float32x4,
+        8 => Float32x4,
+        9 => Float32x4,
+        10 => Float32,
+    ];
+}
+
+impl InstanceRaw {
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: Self::ATTRIBUTES,
+        }
+    }
+}
@@ -1,19 +1,40 @@
-mod camera;
-mod draw_shape;
-mod input;
+pub mod animation;
+pub mod bindings;
+pub mod camera;
+pub mod camera_path;
+mod capture;
+pub mod clock;
+mod cube_face;
+pub mod draw_shape;
+pub mod input;
+pub mod instance;
+pub mod material;
+pub mod mesh;
+pub mod mipmap;
+pub mod model;
+pub mod noise;
+pub mod object;
+mod render_pass;
+pub mod replay;
 mod resources;
-mod rotation;
-mod skybox;
-mod texture;
-mod view;
+pub mod scene;
+pub mod skybox;
+pub mod starfield;
+pub mod texture;
+pub mod view;
 
 use winit::{
-    event::{DeviceEvent, ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
+    event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     window::WindowBuilder,
 };
 
-use crate::draw_shape::DrawShape;
+use crate::{
+    bindings::Bindings,
+    input::InputEvent,
+    replay::{Recorder, Replay},
+    scene::Scene,
+};
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
@@ -56,21 +77,81 @@ pub async fn run() {
 
     // Create display to render view.
     let mut view = view::RenderView::new(&window).await;
-    view.push_shape(DrawShape {
-        vertex_fn: "vs_background",
-        fragment_fn: "fs_texture",
-        vertex_count: 6,
-    });
-    view.push_shape(DrawShape {
-        vertex_fn: "vs_pyramid4",
-        fragment_fn: "fs_main",
-        vertex_count: 12,
-    });
-    view.push_shape(DrawShape {
-        vertex_fn: "vs_pyramid",
-        fragment_fn: "fs_main",
-        vertex_count: 9,
-    });
+    #[cfg(target_arch = "wasm32")]
+    let scene_file = String::from("scene.ron");
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut scene_file = std::env::var("SPACE_SCENE").unwrap_or_else(|_| String::from("scene.ron"));
+
+    // Play back recorded inputs instead of live ones, in the recorded scene.
+    #[cfg(target_arch = "wasm32")]
+    let mut replay: Option<Replay> = None;
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut replay = match std::env::var("SPACE_REPLAY") {
+        Ok(file) => match replay::Recording::read(&file) {
+            Ok(recording) => {
+                if let Some(scene) = &recording.scene {
+                    scene_file = scene.clone();
+                }
+                Some(Replay::new(recording))
+            }
+            Err(e) => {
+                log::error!("Failed to read recording {}: {}", file, e);
+                None
+            }
+        },
+        Err(_) => None,
+    };
+
+    match Scene::load(&scene_file).await {
+        Ok(scene) => {
+            if let Err(e) = view.load_scene(&scene).await {
+                log::error!("Failed to load scene {}: {}", scene_file, e);
+            }
+        }
+        Err(e) => log::error!("Failed to read scene {}: {}", scene_file, e),
+    }
+
+    // Bindings saved from the GUI win over the defaults shipped in `res/`.
+    #[cfg(not(target_arch = "wasm32"))]
+    let bindings = if std::path::Path::new(view::BINDINGS_FILE).exists() {
+        Bindings::read(view::BINDINGS_FILE)
+    } else {
+        Bindings::load("bindings.ron").await
+    };
+    #[cfg(target_arch = "wasm32")]
+    let bindings = Bindings::load("bindings.ron").await;
+    match bindings {
+        Ok(bindings) => view.bindings = bindings,
+        Err(e) => log::error!("Failed to read key bindings: {}", e),
+    }
+
+    if let Some(replay) = replay.as_mut() {
+        replay.start(&mut view);
+    }
+
+    // Record inputs and frame times, saved when the app exits.
+    #[cfg(target_arch = "wasm32")]
+    let mut recorder: Option<(String, Recorder)> = None;
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut recorder = match std::env::var("SPACE_RECORD_INPUT") {
+        // Live input is ignored during a replay, so there would be nothing to record.
+        Ok(_) if replay.is_some() => {
+            log::warn!("Not recording input while replaying");
+            None
+        }
+        Ok(file) => Some((
+            file,
+            Recorder::new(Some(scene_file.clone()), view.bindings.clone()),
+        )),
+        Err(_) => None,
+    };
+
+    // Record every frame of the run as a PNG sequence.
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Ok(dir) = std::env::var("SPACE_RECORD") {
+        view.capture.start_sequence(dir);
+    }
+
     let mut egui_state = egui_winit::State::new(&event_loop);
 
     let mut last_render_time = instant::Instant::now();
@@ -80,12 +161,10 @@ pub async fn run() {
         *control_flow = ControlFlow::Poll;
 
         match event {
-            Event::DeviceEvent {
-                event: DeviceEvent::MouseMotion { delta },
-                ..
-            } => {
-                if view.mouse_pressed {
-                    view.camera_controller.process_mouse(delta.0, delta.1);
+            Event::DeviceEvent { ref event, .. } => {
+                if let Some(event) = InputEvent::from_device_event(event) {
+                    let recorder = recorder.as_mut().map(|(_, recorder)| recorder);
+                    process_live_input(&mut view, event, replay.is_some(), recorder);
                 }
             }
 
@@ -97,7 +176,12 @@ pub async fn run() {
                 if egui_response.repaint {
                     view.egui_repaint = true;
                 }
-                if !egui_response.consumed && !input::process_event(&mut view, event) {
+                let used = !egui_response.consumed
+                    && InputEvent::from_window_event(event).is_some_and(|input| {
+                        let recorder = recorder.as_mut().map(|(_, recorder)| recorder);
+                        process_live_input(&mut view, input, replay.is_some(), recorder)
+                    });
+                if !egui_response.consumed && !used {
                     match event {
                         #[cfg(not(target_arch = "wasm32"))]
                         WindowEvent::CloseRequested
@@ -125,9 +209,23 @@ pub async fn run() {
                 let now = instant::Instant::now();
                 let dt = now - last_render_time;
                 last_render_time = now;
-                view.update(dt);
-
-                let egui_input = egui_state.take_egui_input(&window);
+                // A replay steps with its recorded time steps and overlay
+                // input until it runs out. Live overlay input is dropped.
+                let live_input = egui_state.take_egui_input(&window);
+                let egui_input = match replay.as_mut().and_then(|r| r.step(&mut view)) {
+                    Some(replayed_input) => replayed_input,
+                    None => {
+                        if replay.take().is_some() {
+                            log::info!("Replay finished");
+                        }
+                        view.update(dt);
+                        if let Some((_, recorder)) = recorder.as_mut() {
+                            recorder.record_gui(&live_input);
+                            recorder.end_frame(dt);
+                        }
+                        live_input
+                    }
+                };
                 match view.render(egui_input) {
                     Ok(_) => {}
                     // Reconfigure the surface if lost
@@ -139,6 +237,16 @@ pub async fn run() {
                 }
             }
 
+            Event::LoopDestroyed => {
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Some((file, recorder)) = &recorder {
+                    match recorder.recording.save(file) {
+                        Ok(()) => log::info!("Saved input recording to {}", file),
+                        Err(e) => log::error!("Failed to save {}: {}", file, e),
+                    }
+                }
+            }
+
             Event::MainEventsCleared => {
                 // RedrawRequested will only trigger once, unless we manually
                 // request it.
@@ -149,3 +257,20 @@ pub async fn run() {
         }
     });
 }
+
+/// Apply a live input, recording it if asked. Ignored while a replay drives
+/// the view, so only the recorded inputs count.
+fn process_live_input(
+    view: &mut view::RenderView,
+    event: InputEvent,
+    replaying: bool,
+    recorder: Option<&mut Recorder>,
+) -> bool {
+    if replaying {
+        return false;
+    }
+    if let Some(recorder) = recorder {
+        recorder.record(event);
+    }
+    input::process_input(view, &event)
+}
@@ -0,0 +1,210 @@
+use std::rc::Rc;
+
+use serde::Deserialize;
+
+use crate::texture::Texture;
+
+/// Affine map from mesh texture coordinates to the sampled ones: scaled,
+/// then rotated in degrees about the origin, then offset.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct UvTransform {
+    pub offset: [f32; 2],
+    pub scale: [f32; 2],
+    pub rotation: f32,
+}
+
+impl Default for UvTransform {
+    fn default() -> Self {
+        UvTransform {
+            offset: [0.0; 2],
+            scale: [1.0; 2],
+            rotation: 0.0,
+        }
+    }
+}
+
+impl UvTransform {
+    /// Rows of the 2x3 matrix.
+    fn rows(&self) -> [[f32; 4]; 2] {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let [sx, sy] = self.scale;
+        let [ox, oy] = self.offset;
+        [
+            [cos * sx, -sin * sy, ox, 0.0],
+            [sin * sx, cos * sy, oy, 0.0],
+        ]
+    }
+}
+
+/// Parameters of a shape material, applied by `fs_texture`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct MaterialParams {
+    /// Multiplied with the base texture.
+    pub tint: [f32; 3],
+    /// Fixed for shapes with their own material. The `alpha` parameter only
+    /// animates the opacity of the view's default material.
+    pub opacity: f32,
+    pub uv: UvTransform,
+    /// Added light, multiplied with the emissive texture.
+    pub emissive: [f32; 3],
+}
+
+impl Default for MaterialParams {
+    fn default() -> Self {
+        MaterialParams {
+            tint: [1.0; 3],
+            opacity: 1.0,
+            uv: UvTransform::default(),
+            emissive: [0.0; 3],
+        }
+    }
+}
+
+impl MaterialParams {
+    /// Index of `opacity` among the floats of the uniform, for animating it.
+    pub const OPACITY_INDEX: usize = 3;
+}
+
+/// Matches `Material` in shader.wgsl.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct MaterialRaw {
+    /// Tint and opacity.
+    color: [f32; 4],
+    /// Emissive color, and 1 for a premultiplied base texture.
+    emissive: [f32; 4],
+    uv: [[f32; 4]; 2],
+}
+
+impl MaterialRaw {
+    fn new(params: &MaterialParams, premultiplied_alpha: bool) -> Self {
+        let [r, g, b] = params.tint;
+        let [er, eg, eb] = params.emissive;
+        MaterialRaw {
+            color: [r, g, b, params.opacity],
+            emissive: [er, eg, eb, f32::from(u8::from(premultiplied_alpha))],
+            uv: params.uv.rows(),
+        }
+    }
+}
+
+/// Textures and parameters a shape is drawn with, bound at group 3. Textures
+/// are shared, so several materials can show one with different parameters.
+pub struct ShapeMaterial {
+    pub base_texture: Rc<Texture>,
+    pub emissive_texture: Rc<Texture>,
+    pub params: MaterialParams,
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl ShapeMaterial {
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        base_texture: Rc<Texture>,
+        emissive_texture: Rc<Texture>,
+        params: MaterialParams,
+    ) -> Self {
+        use wgpu::util::DeviceExt;
+        let raw = MaterialRaw::new(&params, base_texture.premultiplied_alpha);
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Shape material"),
+            contents: bytemuck::cast_slice(&[raw]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&base_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&base_texture.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&emissive_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Sampler(&emissive_texture.sampler),
+                },
+            ],
+            label: Some("Shape material"),
+        });
+
+        ShapeMaterial {
+            base_texture,
+            emissive_texture,
+            params,
+            buffer,
+            bind_group,
+        }
+    }
+
+    /// The floats of the uniform, as `Material` in shader.wgsl.
+    pub fn uniform_values(&self) -> Vec<f32> {
+        let raw = MaterialRaw::new(&self.params, self.base_texture.premultiplied_alpha);
+        bytemuck::cast_slice(&[raw]).to_vec()
+    }
+
+    /// How the shape blends with what is behind it: as premultiplied with a
+    /// premultiplied base texture, otherwise by straight alpha.
+    pub fn blend_state(&self) -> wgpu::BlendState {
+        if self.base_texture.premultiplied_alpha {
+            wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING
+        } else {
+            wgpu::BlendState::ALPHA_BLENDING
+        }
+    }
+
+    /// Base texture and sampler, the uniform, then the emissive texture and
+    /// sampler.
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        let texture = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        };
+        let sampler = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        };
+
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                texture(0),
+                sampler(1),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                texture(3),
+                sampler(4),
+            ],
+            label: Some("Shape material"),
+        })
+    }
+}
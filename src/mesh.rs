@@ -0,0 +1,71 @@
+use serde::Deserialize;
+
+/// A vertex type that can be uploaded to a vertex buffer.
+pub trait Vertex: bytemuck::Pod {
+    const ATTRIBUTES: &'static [wgpu::VertexAttribute];
+}
+
+/// Matches `MeshInput` in shader.wgsl.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Deserialize)]
+pub struct ColorVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+    #[serde(default)]
+    pub tex_coords: [f32; 2],
+}
+
+impl Vertex for ColorVertex {
+    const ATTRIBUTES: &'static [wgpu::VertexAttribute] =
+        &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4, 2 => Float32x2];
+}
+
+/// Geometry for a `DrawShape`, kept on the CPU until the shape is pushed.
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    pub vertices: Vec<u8>,
+    pub vertex_stride: wgpu::BufferAddress,
+    pub attributes: Vec<wgpu::VertexAttribute>,
+    pub indices: Option<Vec<u32>>,
+}
+
+impl Mesh {
+    pub fn new<V: Vertex>(vertices: &[V], indices: Option<Vec<u32>>) -> Self {
+        Mesh {
+            vertices: bytemuck::cast_slice(vertices).to_vec(),
+            vertex_stride: std::mem::size_of::<V>() as wgpu::BufferAddress,
+            attributes: V::ATTRIBUTES.to_vec(),
+            indices,
+        }
+    }
+
+    /// Number of indices, or of vertices for a non-indexed mesh.
+    pub fn element_count(&self) -> u32 {
+        match &self.indices {
+            Some(indices) => indices.len() as u32,
+            None => (self.vertices.len() as wgpu::BufferAddress / self.vertex_stride) as u32,
+        }
+    }
+
+    pub fn layout(&self) -> wgpu::VertexBufferLayout<'_> {
+        wgpu::VertexBufferLayout {
+            array_stride: self.vertex_stride,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &self.attributes,
+        }
+    }
+}
+
+/// Vertices and optional indices as written in a scene file.
+#[derive(Debug, Deserialize)]
+pub struct MeshData {
+    pub vertices: Vec<ColorVertex>,
+    #[serde(default)]
+    pub indices: Option<Vec<u32>>,
+}
+
+impl MeshData {
+    pub fn to_mesh(&self) -> Mesh {
+        Mesh::new(&self.vertices, self.indices.clone())
+    }
+}
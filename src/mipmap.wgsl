@@ -0,0 +1,36 @@
+// Downsamples one mip level into the next. Sampling halfway between four
+// texels with a linear filter averages each 2x2 block.
+
+struct BlitOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_blit(@builtin(vertex_index) vertex_index: u32) -> BlitOutput {
+    // One large triangle covering the level
+    let tmp1 = i32(vertex_index) / 2;
+    let tmp2 = i32(vertex_index) & 1;
+    var out: BlitOutput;
+    out.position = vec4<f32>(f32(tmp1) * 4.0 - 1.0, f32(tmp2) * 4.0 - 1.0, 0.0, 1.0);
+    out.uv = vec2<f32>(f32(tmp1) * 2.0, 1.0 - f32(tmp2) * 2.0);
+    return out;
+}
+
+struct Blit {
+    level: f32,
+};
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+@group(0) @binding(2)
+var<uniform> blit: Blit;
+
+@fragment
+fn fs_blit(in: BlitOutput) -> @location(0) vec4<f32> {
+    // The GL backend ignores the mip range of views, so the source level is
+    // picked explicitly. Elsewhere the view holds only that level.
+    return textureSampleLevel(t_source, s_source, in.uv, blit.level);
+}
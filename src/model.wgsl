@@ -0,0 +1,119 @@
+struct Rotation {
+    jitter: vec4<f32>,
+    angle: f32,
+};
+@group(0) @binding(0)
+var<uniform> r: Rotation;
+
+fn rotation_y(angle: f32) -> mat4x4<f32> {
+    let c = cos(angle);
+    let s = sin(angle);
+    return mat4x4<f32>(
+        vec4<f32>(c, 0.0, -s, 0.0),
+        vec4<f32>(0.0, 1.0, 0.0, 0.0),
+        vec4<f32>(s, 0.0, c, 0.0),
+        vec4<f32>(0.0, 0.0, 0.0, 1.0),
+    );
+}
+
+struct Object {
+    model: mat4x4<f32>,
+    normal: mat4x4<f32>,
+};
+@group(0) @binding(1)
+var<uniform> object: Object;
+
+struct Camera {
+    view_position: vec4<f32>,
+    view_proj: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    proj_inv: mat4x4<f32>,
+    view: mat4x4<f32>,
+    // 1.0, or 0.0 for reverse-Z projections.
+    far_depth: f32,
+};
+@group(1) @binding(0)
+var<uniform> camera: Camera;
+
+struct ModelInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(3) tangent: vec3<f32>,
+    @location(4) bitangent: vec3<f32>,
+};
+
+struct ModelOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) tangent_light: vec3<f32>,
+    @location(2) tangent_view: vec3<f32>,
+};
+
+@vertex
+fn vs_model(in: ModelInput) -> ModelOutput {
+    // The spin is a pure rotation, so it also transforms normals.
+    let spin = rotation_y(r.angle);
+    let normal_matrix =
+        mat3x3<f32>(object.normal[0].xyz, object.normal[1].xyz, object.normal[2].xyz) *
+        mat3x3<f32>(spin[0].xyz, spin[1].xyz, spin[2].xyz);
+    let tangent_matrix = transpose(mat3x3<f32>(
+        normalize(normal_matrix * in.tangent),
+        normalize(normal_matrix * in.bitangent),
+        normalize(normal_matrix * in.normal),
+    ));
+    let light_direction = normalize(vec3<f32>(-0.5, 1.0, 0.75));
+
+    let world_position = object.model * spin * vec4<f32>(in.position, 1.0);
+
+    var out: ModelOutput;
+    out.clip_position = camera.view_proj * world_position;
+    out.tex_coords = in.tex_coords;
+    out.tangent_light = tangent_matrix * light_direction;
+    out.tangent_view = tangent_matrix * (camera.view_position.xyz - world_position.xyz);
+    return out;
+}
+
+@group(3) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(3) @binding(1)
+var s_diffuse: sampler;
+@group(3) @binding(2)
+var t_normal: texture_2d<f32>;
+@group(3) @binding(3)
+var s_normal: sampler;
+
+struct Material {
+    base_color: vec4<f32>,
+    metallic: f32,
+    roughness: f32,
+};
+@group(3) @binding(4)
+var<uniform> material: Material;
+@group(3) @binding(5)
+var t_metallic_roughness: texture_2d<f32>;
+@group(3) @binding(6)
+var s_metallic_roughness: sampler;
+
+@fragment
+fn fs_model(in: ModelOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_diffuse, s_diffuse, in.tex_coords) * material.base_color;
+    let normal = normalize(textureSample(t_normal, s_normal, in.tex_coords).xyz * 2.0 - 1.0);
+    let light = normalize(in.tangent_light);
+    let half_dir = normalize(light + normalize(in.tangent_view));
+    // Metallic in blue and roughness in green, as in glTF.
+    let metal_rough = textureSample(t_metallic_roughness, s_metallic_roughness, in.tex_coords);
+    let metallic = material.metallic * metal_rough.b;
+    let roughness = material.roughness * metal_rough.g;
+
+    // Blinn-Phong approximation of metallic-roughness: rough surfaces get a
+    // broad dim highlight, and metals tint it with the base color.
+    let alpha = max(roughness * roughness, 0.01);
+    let shininess = 2.0 / (alpha * alpha) - 2.0;
+    let specular_color = mix(vec3<f32>(0.04), color.rgb, metallic);
+    let specular = pow(max(dot(normal, half_dir), 0.0), shininess) * specular_color;
+
+    let diffuse = max(dot(normal, light), 0.0) * (1.0 - metallic);
+    let ambient = 0.2;
+    return vec4<f32>(color.rgb * (ambient + diffuse) + specular, color.a);
+}
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+
+/// `∫ (1 - u) fade(u) du` over one lattice cell.
+const CELL_INTEGRAL: f32 = 1.0 / 7.0;
+
+/// Seekable 1D gradient noise summed over octaves, as a function of time.
+/// Unlike an iterator of samples, it gives the same value for the same time
+/// whatever the frame rate, and can jump to any time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Noise {
+    pub seed: u32,
+    /// Lattice cells per second of the first octave.
+    pub frequency: f32,
+    pub octaves: u32,
+    /// Frequency multiplier from one octave to the next.
+    pub lacunarity: f32,
+    /// Amplitude multiplier from one octave to the next.
+    pub gain: f32,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Noise {
+            seed: 0,
+            frequency: 1.0,
+            octaves: 1,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+}
+
+impl Noise {
+    pub fn new(seed: u32, frequency: f32, octaves: u32) -> Self {
+        Noise {
+            seed,
+            frequency,
+            octaves,
+            ..Default::default()
+        }
+    }
+
+    /// The noise at `time` seconds, in `0.0..=1.0`.
+    pub fn sample(&self, time: f32) -> f32 {
+        let sum: f32 = self
+            .octaves()
+            .map(|(seed, frequency, amplitude)| amplitude * gradient(seed, time * frequency))
+            .sum();
+        0.5 + sum / self.total_amplitude()
+    }
+
+    /// `sample` integrated from zero to `time`, in closed form so it can be
+    /// evaluated at any time without stepping there.
+    pub fn integral(&self, time: f32) -> f32 {
+        let sum: f32 = self
+            .octaves()
+            .map(|(seed, frequency, amplitude)| {
+                amplitude * gradient_integral(seed, time * frequency) / frequency
+            })
+            .sum();
+        0.5 * time + sum / self.total_amplitude()
+    }
+
+    /// Seed, frequency and amplitude of each octave.
+    fn octaves(&self) -> impl Iterator<Item = (u32, f32, f32)> + '_ {
+        (0..self.octaves.max(1)).map(move |octave| {
+            (
+                self.seed.wrapping_add(octave.wrapping_mul(0x9e37_79b9)),
+                self.frequency * self.lacunarity.powi(octave as i32),
+                self.gain.powi(octave as i32),
+            )
+        })
+    }
+
+    fn total_amplitude(&self) -> f32 {
+        (0..self.octaves.max(1))
+            .map(|octave| self.gain.powi(octave as i32))
+            .sum()
+    }
+}
+
+/// Gradient in `-1.0..=1.0` at lattice point `cell`.
+fn slope(seed: u32, cell: i32) -> f32 {
+    // Murmur3 finalizer.
+    let mut h = seed ^ (cell as u32).wrapping_mul(0xcc9e_2d51);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^= h >> 16;
+    h as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+/// Perlin noise in one dimension, in `-0.5..=0.5`.
+fn gradient(seed: u32, x: f32) -> f32 {
+    let cell = x.floor();
+    let t = x - cell;
+    let (g0, g1) = (slope(seed, cell as i32), slope(seed, cell as i32 + 1));
+    let s = fade(t);
+    g0 * t * (1.0 - s) + g1 * (t - 1.0) * s
+}
+
+/// `gradient` integrated from zero to `x`. Whole cells telescope to
+/// `CELL_INTEGRAL * (g(0) - g(cell))`, leaving only the partial cell.
+fn gradient_integral(seed: u32, x: f32) -> f32 {
+    let cell = x.floor();
+    let t = x - cell;
+    let (g0, g1) = (slope(seed, cell as i32), slope(seed, cell as i32 + 1));
+
+    // ∫ u fade(u) du and ∫ fade(u) du from zero to t.
+    let t4 = t * t * t * t;
+    let u_fade = t4 * t * (t * (t * 6.0 / 7.0 - 2.5) + 2.0);
+    let fade = t4 * (t * (t - 3.0) + 2.5);
+    let partial = g0 * (0.5 * t * t - u_fade) + g1 * (u_fade - fade);
+
+    CELL_INTEGRAL * (slope(seed, 0) - g0) + partial
+}
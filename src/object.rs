@@ -0,0 +1,183 @@
+use cgmath::{Matrix, SquareMatrix};
+use serde::Deserialize;
+
+/// Placement of an object in world space. Rotation is in Euler degrees.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct Transform {
+    pub translation: [f32; 3],
+    pub rotation: [f32; 3],
+    pub scale: [f32; 3],
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform {
+            translation: [0.0; 3],
+            rotation: [0.0; 3],
+            scale: [1.0; 3],
+        }
+    }
+}
+
+impl Transform {
+    pub fn to_matrix(&self) -> cgmath::Matrix4<f32> {
+        let [x, y, z] = self.rotation;
+        let [sx, sy, sz] = self.scale;
+        cgmath::Matrix4::from_translation(self.translation.into())
+            * cgmath::Matrix4::from_angle_z(cgmath::Deg(z))
+            * cgmath::Matrix4::from_angle_y(cgmath::Deg(y))
+            * cgmath::Matrix4::from_angle_x(cgmath::Deg(x))
+            * cgmath::Matrix4::from_nonuniform_scale(sx, sy, sz)
+    }
+}
+
+/// Matches `Object` in shader.wgsl and model.wgsl.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ObjectRaw {
+    model: [[f32; 4]; 4],
+    /// Inverse transpose of the model matrix, for normals.
+    normal: [[f32; 4]; 4],
+}
+
+impl From<&Transform> for ObjectRaw {
+    fn from(transform: &Transform) -> Self {
+        let model = transform.to_matrix();
+        let normal = model.invert().unwrap_or(model).transpose();
+        ObjectRaw {
+            model: model.into(),
+            normal: normal.into(),
+        }
+    }
+}
+
+/// Per-object model matrices in one uniform buffer. Bound at group 0 next to
+/// the rotation uniform, with a dynamic offset selecting the object.
+pub struct ObjectUniforms {
+    buffer: wgpu::Buffer,
+    stride: wgpu::BufferAddress,
+    capacity: usize,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl ObjectUniforms {
+    pub fn new(device: &wgpu::Device, rotation: &wgpu::Buffer) -> Self {
+        let size = std::mem::size_of::<ObjectRaw>() as wgpu::BufferAddress;
+        let align = device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let stride = size.div_ceil(align) * align;
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Object"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(size),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let capacity = 16;
+        let (buffer, bind_group) =
+            Self::create_buffer(device, &bind_group_layout, rotation, stride, capacity);
+
+        ObjectUniforms {
+            buffer,
+            stride,
+            capacity,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    fn create_buffer(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        rotation: &wgpu::Buffer,
+        stride: wgpu::BufferAddress,
+        capacity: usize,
+    ) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Object"),
+            size: stride * capacity as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Object"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: rotation.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &buffer,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(
+                            std::mem::size_of::<ObjectRaw>() as wgpu::BufferAddress
+                        ),
+                    }),
+                },
+            ],
+        });
+        (buffer, bind_group)
+    }
+
+    /// Upload one transform per object, growing the buffer when needed.
+    pub fn write<'a, I>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        rotation: &wgpu::Buffer,
+        transforms: I,
+    ) where
+        I: IntoIterator<Item = &'a Transform>,
+    {
+        let mut data = Vec::new();
+        for transform in transforms {
+            let start = data.len();
+            data.extend_from_slice(bytemuck::bytes_of(&ObjectRaw::from(transform)));
+            data.resize(start + self.stride as usize, 0);
+        }
+
+        let count = data.len() / self.stride as usize;
+        if count > self.capacity {
+            self.capacity = count.next_power_of_two();
+            (self.buffer, self.bind_group) = Self::create_buffer(
+                device,
+                &self.bind_group_layout,
+                rotation,
+                self.stride,
+                self.capacity,
+            );
+        }
+        if !data.is_empty() {
+            queue.write_buffer(&self.buffer, 0, &data);
+        }
+    }
+
+    /// Dynamic offset of the `index`th object written.
+    pub fn offset(&self, index: usize) -> wgpu::DynamicOffset {
+        (index as wgpu::BufferAddress * self.stride) as wgpu::DynamicOffset
+    }
+}
@@ -0,0 +1,70 @@
+use std::ops::Range;
+
+/// Bind group slots used by the shaders.
+const BIND_GROUPS: usize = 4;
+
+/// A render pass that skips pipeline and bind group changes which wouldn't
+/// change anything, so many shapes can share one pass cheaply. The wrapped
+/// pass isn't exposed, so nothing changes its state behind the tracking.
+pub struct TrackedRenderPass<'a> {
+    pass: wgpu::RenderPass<'a>,
+    pipeline: Option<&'a wgpu::RenderPipeline>,
+    bind_groups: [Option<(&'a wgpu::BindGroup, Vec<wgpu::DynamicOffset>)>; BIND_GROUPS],
+}
+
+impl<'a> TrackedRenderPass<'a> {
+    pub fn new(pass: wgpu::RenderPass<'a>) -> Self {
+        TrackedRenderPass {
+            pass,
+            pipeline: None,
+            bind_groups: Default::default(),
+        }
+    }
+
+    pub fn set_pipeline(&mut self, pipeline: &'a wgpu::RenderPipeline) {
+        if self
+            .pipeline
+            .is_some_and(|current| std::ptr::eq(current, pipeline))
+        {
+            return;
+        }
+        self.pass.set_pipeline(pipeline);
+        self.pipeline = Some(pipeline);
+    }
+
+    pub fn set_bind_group(
+        &mut self,
+        index: u32,
+        bind_group: &'a wgpu::BindGroup,
+        offsets: &[wgpu::DynamicOffset],
+    ) {
+        let slot = &mut self.bind_groups[index as usize];
+        if let Some((current, current_offsets)) = slot {
+            if std::ptr::eq(*current, bind_group) && current_offsets.as_slice() == offsets {
+                return;
+            }
+        }
+        self.pass.set_bind_group(index, bind_group, offsets);
+        *slot = Some((bind_group, offsets.to_vec()));
+    }
+
+    pub fn set_vertex_buffer(&mut self, slot: u32, buffer_slice: wgpu::BufferSlice<'a>) {
+        self.pass.set_vertex_buffer(slot, buffer_slice);
+    }
+
+    pub fn set_index_buffer(
+        &mut self,
+        buffer_slice: wgpu::BufferSlice<'a>,
+        index_format: wgpu::IndexFormat,
+    ) {
+        self.pass.set_index_buffer(buffer_slice, index_format);
+    }
+
+    pub fn draw(&mut self, vertices: Range<u32>, instances: Range<u32>) {
+        self.pass.draw(vertices, instances);
+    }
+
+    pub fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>) {
+        self.pass.draw_indexed(indices, base_vertex, instances);
+    }
+}
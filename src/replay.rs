@@ -0,0 +1,155 @@
+use instant::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bindings::Bindings,
+    input::{self, InputEvent},
+    resources::load_string,
+    view::RenderView,
+};
+
+/// Inputs received before one `RenderView::update`, and its time step.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Frame {
+    pub events: Vec<InputEvent>,
+    pub dt: Duration,
+    /// Input of the overlay rendered after the update, so changes made in
+    /// the GUI replay too.
+    #[serde(default)]
+    pub gui: egui::RawInput,
+}
+
+/// A recorded run, saved as RON. Replaying it against the same scene
+/// reproduces the camera, rotation and GUI changes of every frame.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    /// Scene loaded when recording started.
+    #[serde(default)]
+    pub scene: Option<String>,
+    /// Bindings the inputs were mapped with.
+    #[serde(default)]
+    pub bindings: Option<Bindings>,
+    pub frames: Vec<Frame>,
+}
+
+impl Recording {
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        Ok(ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(text)?)
+    }
+
+    /// Load a recording from `res/`, or from an absolute path on native.
+    pub async fn load(file_name: &str) -> anyhow::Result<Self> {
+        let text = load_string(file_name).await?;
+        Self::parse(&text).map_err(|e| anyhow::anyhow!("{}: {}", file_name, e))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read<P: AsRef<std::path::Path>>(path: P) -> anyhow::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> anyhow::Result<()> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Sum of all frame time steps.
+    pub fn duration(&self) -> Duration {
+        self.frames.iter().map(|frame| frame.dt).sum()
+    }
+}
+
+/// Collects inputs frame by frame while the app runs.
+#[derive(Debug, Default)]
+pub struct Recorder {
+    pub recording: Recording,
+    events: Vec<InputEvent>,
+    gui: egui::RawInput,
+}
+
+impl Recorder {
+    pub fn new(scene: Option<String>, bindings: Bindings) -> Self {
+        Recorder {
+            recording: Recording {
+                scene,
+                bindings: Some(bindings),
+                frames: Vec::new(),
+            },
+            events: Vec::new(),
+            gui: egui::RawInput::default(),
+        }
+    }
+
+    pub fn record(&mut self, event: InputEvent) {
+        self.events.push(event);
+    }
+
+    /// Keep the overlay input the current frame is rendered with.
+    pub fn record_gui(&mut self, input: &egui::RawInput) {
+        self.gui = input.clone();
+    }
+
+    /// Close the frame updated with `dt`.
+    pub fn end_frame(&mut self, dt: Duration) {
+        self.recording.frames.push(Frame {
+            events: std::mem::take(&mut self.events),
+            dt,
+            gui: std::mem::take(&mut self.gui),
+        });
+    }
+}
+
+/// Feeds a recording back into a view, one frame per `step`.
+#[derive(Debug)]
+pub struct Replay {
+    recording: Recording,
+    frame: usize,
+}
+
+impl Replay {
+    pub fn new(recording: Recording) -> Self {
+        Replay {
+            recording,
+            frame: 0,
+        }
+    }
+
+    /// Use the recorded bindings, so keys map to the same actions.
+    pub fn start(&mut self, view: &mut RenderView) {
+        self.frame = 0;
+        if let Some(bindings) = &self.recording.bindings {
+            view.bindings = bindings.clone();
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.recording.frames.len()
+    }
+
+    /// Apply the next frame's inputs and update the view with its time step,
+    /// returning the overlay input to render the frame with. Returns `None`
+    /// once the recording is exhausted.
+    pub fn step(&mut self, view: &mut RenderView) -> Option<egui::RawInput> {
+        let frame = self.recording.frames.get(self.frame)?;
+        for event in &frame.events {
+            input::process_input(view, event);
+        }
+        view.update(frame.dt);
+        self.frame += 1;
+        Some(frame.gui.clone())
+    }
+
+    /// Play and render the whole recording, e.g. to read its last frame
+    /// headless.
+    pub fn run(&mut self, view: &mut RenderView) -> Result<(), wgpu::SurfaceError> {
+        self.start(view);
+        while let Some(gui) = self.step(view) {
+            view.render(gui)?;
+        }
+        Ok(())
+    }
+}
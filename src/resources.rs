@@ -1,10 +1,14 @@
-//use std::io::{BufReader, Cursor};
+use std::io::{BufReader, Cursor};
 
 use cfg_if::cfg_if;
-//use wgpu::util::DeviceExt;
 
-//use crate::{model, texture};
-use crate::texture;
+use cgmath::{InnerSpace, Matrix, SquareMatrix, Transform};
+
+use crate::{
+    mipmap::MipmapGenerator,
+    model,
+    texture::{self, Filter, TextureOptions, Wrap},
+};
 
 #[cfg(target_arch = "wasm32")]
 fn format_url(file_name: &str) -> reqwest::Url {
@@ -19,7 +23,7 @@ fn format_url(file_name: &str) -> reqwest::Url {
     base.join(file_name).unwrap()
 }
 
-pub async fn _load_string(file_name: &str) -> anyhow::Result<String> {
+pub async fn load_string(file_name: &str) -> anyhow::Result<String> {
     cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
             let url = format_url(file_name);
@@ -61,19 +65,65 @@ pub async fn load_binary(file_name: &str) -> anyhow::Result<Vec<u8>> {
 
 pub async fn load_texture(
     file_name: &str,
+    options: &TextureOptions,
     device: &wgpu::Device,
     queue: &wgpu::Queue,
+    mipmaps: &MipmapGenerator,
 ) -> anyhow::Result<texture::Texture> {
     //println!("load_texture:file_name:>{file_name}<");
     let data = load_binary(file_name).await?;
-    texture::Texture::from_bytes(device, queue, &data, file_name, false)
+    texture::Texture::from_bytes(device, queue, mipmaps, &data, file_name, options)
+}
+
+/// Resolve `name` relative to the directory of `file_name` within `res/`.
+fn sibling_path(file_name: &str, name: &str) -> String {
+    match file_name.rsplit_once('/') {
+        Some((dir, _)) => format!("{dir}/{name}"),
+        None => name.to_string(),
+    }
+}
+
+/// Load a material texture, or a 1x1 texture of `fallback` if the material has none.
+async fn load_material_texture(
+    model_file: &str,
+    texture_file: &str,
+    fallback: [u8; 4],
+    options: &TextureOptions,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    mipmaps: &MipmapGenerator,
+) -> anyhow::Result<texture::Texture> {
+    if texture_file.is_empty() {
+        return solid_texture(fallback, options, device, queue, mipmaps);
+    }
+    let path = sibling_path(model_file, texture_file);
+    let data = load_binary(&path).await?;
+    texture::Texture::from_bytes(device, queue, mipmaps, &data, &path, options)
+}
+
+/// A 1x1 texture of a single color.
+pub fn solid_texture(
+    color: [u8; 4],
+    options: &TextureOptions,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    mipmaps: &MipmapGenerator,
+) -> anyhow::Result<texture::Texture> {
+    let img =
+        image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(1, 1, image::Rgba(color)));
+    texture::Texture::from_image(device, queue, mipmaps, &img, None, options)
 }
 
-/*
+pub const WHITE: [u8; 4] = [255, 255, 255, 255];
+const FLAT_NORMAL: [u8; 4] = [128, 128, 255, 255];
+
+/// Load a Wavefront OBJ model with its MTL materials. Material and texture
+/// files are resolved relative to the OBJ file.
 pub async fn load_model(
     file_name: &str,
     device: &wgpu::Device,
     queue: &wgpu::Queue,
+    mipmaps: &MipmapGenerator,
     layout: &wgpu::BindGroupLayout,
 ) -> anyhow::Result<model::Model> {
     let obj_text = load_string(file_name).await?;
@@ -89,7 +139,9 @@ pub async fn load_model(
             ..Default::default()
         },
         |p| async move {
-            let mat_text = load_string(&p).await.unwrap();
+            let mat_text = load_string(&sibling_path(file_name, &p))
+                .await
+                .map_err(|_| tobj::LoadError::OpenFileFailed)?;
             tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(mat_text)))
         },
     )
@@ -97,13 +149,53 @@ pub async fn load_model(
 
     let mut materials = Vec::new();
     for m in obj_materials? {
-        let diffuse_texture = load_texture(&m.diffuse_texture, device, queue).await?;
-        let normal_texture = load_texture(&m.normal_texture, device, queue).await?;
+        let diffuse_texture = load_material_texture(
+            file_name,
+            &m.diffuse_texture,
+            WHITE,
+            &TextureOptions::default(),
+            device,
+            queue,
+            mipmaps,
+        )
+        .await?;
+        let normal_texture = load_material_texture(
+            file_name,
+            &m.normal_texture,
+            FLAT_NORMAL,
+            &TextureOptions::linear(),
+            device,
+            queue,
+            mipmaps,
+        )
+        .await?;
+        // Map the Phong specular exponent onto a roughness.
+        let roughness = (2.0 / (m.shininess + 2.0)).sqrt();
+        let [r, g, b] = m.diffuse;
         materials.push(model::Material::new(
             device,
             &m.name,
             diffuse_texture,
             normal_texture,
+            solid_texture(WHITE, &TextureOptions::linear(), device, queue, mipmaps)?,
+            model::MaterialUniform::new([r, g, b, 1.0], 0.0, roughness),
+            layout,
+        ));
+    }
+    if materials.is_empty() {
+        materials.push(model::Material::new(
+            device,
+            "default",
+            solid_texture(WHITE, &TextureOptions::default(), device, queue, mipmaps)?,
+            solid_texture(
+                FLAT_NORMAL,
+                &TextureOptions::linear(),
+                device,
+                queue,
+                mipmaps,
+            )?,
+            solid_texture(WHITE, &TextureOptions::linear(), device, queue, mipmaps)?,
+            model::MaterialUniform::default(),
             layout,
         ));
     }
@@ -118,106 +210,335 @@ pub async fn load_model(
                         m.mesh.positions[i * 3 + 1],
                         m.mesh.positions[i * 3 + 2],
                     ],
-                    tex_coords: [m.mesh.texcoords[i * 2], m.mesh.texcoords[i * 2 + 1]],
-                    normal: [
-                        m.mesh.normals[i * 3],
-                        m.mesh.normals[i * 3 + 1],
-                        m.mesh.normals[i * 3 + 2],
-                    ],
+                    tex_coords: if m.mesh.texcoords.is_empty() {
+                        [0.0; 2]
+                    } else {
+                        [m.mesh.texcoords[i * 2], m.mesh.texcoords[i * 2 + 1]]
+                    },
+                    normal: if m.mesh.normals.is_empty() {
+                        [0.0; 3]
+                    } else {
+                        [
+                            m.mesh.normals[i * 3],
+                            m.mesh.normals[i * 3 + 1],
+                            m.mesh.normals[i * 3 + 2],
+                        ]
+                    },
                     tangent: [0.0; 3],
                     bitangent: [0.0; 3],
                 })
                 .collect::<Vec<_>>();
 
-            let indices = &m.mesh.indices;
-            let mut triangles_included = vec![0; vertices.len()];
-
-            // Calculate tangents and bitangets. We're going to
-            // use the triangles, so we need to loop through the
-            // indices in chunks of 3
-            for c in indices.chunks(3) {
-                let v0 = vertices[c[0] as usize];
-                let v1 = vertices[c[1] as usize];
-                let v2 = vertices[c[2] as usize];
-
-                let pos0: cgmath::Vector3<_> = v0.position.into();
-                let pos1: cgmath::Vector3<_> = v1.position.into();
-                let pos2: cgmath::Vector3<_> = v2.position.into();
-
-                let uv0: cgmath::Vector2<_> = v0.tex_coords.into();
-                let uv1: cgmath::Vector2<_> = v1.tex_coords.into();
-                let uv2: cgmath::Vector2<_> = v2.tex_coords.into();
-
-                // Calculate the edges of the triangle
-                let delta_pos1 = pos1 - pos0;
-                let delta_pos2 = pos2 - pos0;
-
-                // This will give us a direction to calculate the
-                // tangent and bitangent
-                let delta_uv1 = uv1 - uv0;
-                let delta_uv2 = uv2 - uv0;
-
-                // Solving the following system of equations will
-                // give us the tangent and bitangent.
-                //     delta_pos1 = delta_uv1.x * T + delta_u.y * B
-                //     delta_pos2 = delta_uv2.x * T + delta_uv2.y * B
-                // Luckily, the place I found this equation provided
-                // the solution!
-                let r = 1.0 / (delta_uv1.x * delta_uv2.y - delta_uv1.y * delta_uv2.x);
-                let tangent = (delta_pos1 * delta_uv2.y - delta_pos2 * delta_uv1.y) * r;
-                // We flip the bitangent to enable right-handed normal
-                // maps with wgpu texture coordinate system
-                let bitangent = (delta_pos2 * delta_uv1.x - delta_pos1 * delta_uv2.x) * -r;
-
-                // We'll use the same tangent/bitangent for each vertex in the triangle
-                vertices[c[0] as usize].tangent =
-                    (tangent + cgmath::Vector3::from(vertices[c[0] as usize].tangent)).into();
-                vertices[c[1] as usize].tangent =
-                    (tangent + cgmath::Vector3::from(vertices[c[1] as usize].tangent)).into();
-                vertices[c[2] as usize].tangent =
-                    (tangent + cgmath::Vector3::from(vertices[c[2] as usize].tangent)).into();
-                vertices[c[0] as usize].bitangent =
-                    (bitangent + cgmath::Vector3::from(vertices[c[0] as usize].bitangent)).into();
-                vertices[c[1] as usize].bitangent =
-                    (bitangent + cgmath::Vector3::from(vertices[c[1] as usize].bitangent)).into();
-                vertices[c[2] as usize].bitangent =
-                    (bitangent + cgmath::Vector3::from(vertices[c[2] as usize].bitangent)).into();
-
-                // Used to average the tangents/bitangents
-                triangles_included[c[0] as usize] += 1;
-                triangles_included[c[1] as usize] += 1;
-                triangles_included[c[2] as usize] += 1;
-            }
+            model::compute_tangents(&mut vertices, &m.mesh.indices);
+
+            model::Mesh::new(
+                device,
+                file_name,
+                &vertices,
+                &m.mesh.indices,
+                m.mesh.material_id.unwrap_or(0),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Ok(model::Model {
+        meshes,
+        materials,
+        cameras: Vec::new(),
+        transform: Default::default(),
+    })
+}
+
+/// Load an external glTF resource, relative to the glTF file, or decode an
+/// embedded base64 data URI.
+async fn load_gltf_uri(file_name: &str, uri: &str) -> anyhow::Result<Vec<u8>> {
+    use base64::Engine;
+
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (_, payload) = data
+            .split_once(";base64,")
+            .ok_or_else(|| anyhow::anyhow!("{file_name}: data URIs must be base64 encoded"))?;
+        return base64::engine::general_purpose::STANDARD
+            .decode(payload)
+            .map_err(|e| anyhow::anyhow!("{file_name}: invalid data URI: {e}"));
+    }
+    load_binary(&sibling_path(file_name, uri)).await
+}
+
+/// Texture options of a glTF sampler. Unset filters stay linear.
+fn sampler_options(sampler: &gltf::texture::Sampler, srgb: bool) -> TextureOptions {
+    use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+
+    let wrap = |mode| match mode {
+        WrappingMode::ClampToEdge => Wrap::Clamp,
+        WrappingMode::MirroredRepeat => Wrap::Mirror,
+        WrappingMode::Repeat => Wrap::Repeat,
+    };
+    let (min_filter, mipmap_filter) = match sampler.min_filter() {
+        Some(MinFilter::Nearest | MinFilter::NearestMipmapNearest) => {
+            (Filter::Nearest, Filter::Nearest)
+        }
+        Some(MinFilter::NearestMipmapLinear) => (Filter::Nearest, Filter::Linear),
+        Some(MinFilter::LinearMipmapNearest) => (Filter::Linear, Filter::Nearest),
+        Some(MinFilter::Linear | MinFilter::LinearMipmapLinear) | None => {
+            (Filter::Linear, Filter::Linear)
+        }
+    };
+    TextureOptions {
+        wrap_u: wrap(sampler.wrap_s()),
+        wrap_v: wrap(sampler.wrap_t()),
+        mag_filter: match sampler.mag_filter() {
+            Some(MagFilter::Nearest) => Filter::Nearest,
+            Some(MagFilter::Linear) | None => Filter::Linear,
+        },
+        min_filter,
+        mipmap_filter,
+        srgb,
+        ..Default::default()
+    }
+}
+
+/// Load a glTF 2.0 model (`.gltf` or `.glb`). Node transforms of the default
+/// scene are baked into the vertices, and cameras are returned with the model.
+pub async fn load_gltf(
+    file_name: &str,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    mipmaps: &MipmapGenerator,
+    layout: &wgpu::BindGroupLayout,
+) -> anyhow::Result<model::Model> {
+    let gltf = gltf::Gltf::from_slice(&load_binary(file_name).await?)?;
 
-            // Average the tangents/bitangents
-            for (i, n) in triangles_included.into_iter().enumerate() {
-                let denom = 1.0 / n as f32;
-                let mut v = &mut vertices[i];
-                v.tangent = (cgmath::Vector3::from(v.tangent) * denom).into();
-                v.bitangent = (cgmath::Vector3::from(v.bitangent) * denom).into();
+    let mut buffers = Vec::new();
+    for buffer in gltf.buffers() {
+        buffers.push(match buffer.source() {
+            gltf::buffer::Source::Bin => gltf
+                .blob
+                .clone()
+                .ok_or_else(|| anyhow::anyhow!("{file_name}: missing binary chunk"))?,
+            gltf::buffer::Source::Uri(uri) => load_gltf_uri(file_name, uri).await?,
+        });
+    }
+
+    let mut images = Vec::new();
+    for image in gltf.images() {
+        images.push(match image.source() {
+            gltf::image::Source::View { view, .. } => {
+                let start = view.offset();
+                buffers
+                    .get(view.buffer().index())
+                    .and_then(|buffer| buffer.get(start..start + view.length()))
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "{file_name}: image {} is outside its buffer",
+                            image.index()
+                        )
+                    })?
+                    .to_vec()
             }
+            gltf::image::Source::Uri { uri, .. } => load_gltf_uri(file_name, uri).await?,
+        });
+    }
+    let load_image = |texture: gltf::Texture, srgb: bool| {
+        let index = texture.source().index();
+        texture::Texture::from_bytes(
+            device,
+            queue,
+            mipmaps,
+            &images[index],
+            &format!("{file_name} image {index}"),
+            &sampler_options(&texture.sampler(), srgb),
+        )
+    };
+
+    let mut materials = Vec::new();
+    for material in gltf.materials() {
+        let pbr = material.pbr_metallic_roughness();
+        let diffuse_texture = match pbr.base_color_texture() {
+            Some(info) => load_image(info.texture(), true)?,
+            None => solid_texture(WHITE, &TextureOptions::default(), device, queue, mipmaps)?,
+        };
+        let normal_texture = match material.normal_texture() {
+            Some(normal) => load_image(normal.texture(), false)?,
+            None => solid_texture(
+                FLAT_NORMAL,
+                &TextureOptions::linear(),
+                device,
+                queue,
+                mipmaps,
+            )?,
+        };
+        let metallic_roughness_texture = match pbr.metallic_roughness_texture() {
+            Some(info) => load_image(info.texture(), false)?,
+            None => solid_texture(WHITE, &TextureOptions::linear(), device, queue, mipmaps)?,
+        };
+        materials.push(model::Material::new(
+            device,
+            material.name().unwrap_or(file_name),
+            diffuse_texture,
+            normal_texture,
+            metallic_roughness_texture,
+            model::MaterialUniform::new(
+                pbr.base_color_factor(),
+                pbr.metallic_factor(),
+                pbr.roughness_factor(),
+            ),
+            layout,
+        ));
+    }
+    // For primitives without a material.
+    let default_material = materials.len();
+    materials.push(model::Material::new(
+        device,
+        "default",
+        solid_texture(WHITE, &TextureOptions::default(), device, queue, mipmaps)?,
+        solid_texture(
+            FLAT_NORMAL,
+            &TextureOptions::linear(),
+            device,
+            queue,
+            mipmaps,
+        )?,
+        solid_texture(WHITE, &TextureOptions::linear(), device, queue, mipmaps)?,
+        model::MaterialUniform::default(),
+        layout,
+    ));
+
+    let scene = gltf
+        .default_scene()
+        .or_else(|| gltf.scenes().next())
+        .ok_or_else(|| anyhow::anyhow!("{file_name}: no scene"))?;
+
+    let mut meshes = Vec::new();
+    let mut cameras = Vec::new();
+    let mut nodes: Vec<_> = scene
+        .nodes()
+        .map(|node| (node, cgmath::Matrix4::identity()))
+        .collect();
+    while let Some((node, parent)) = nodes.pop() {
+        let world = parent * cgmath::Matrix4::from(node.transform().matrix());
 
-            let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some(&format!("{:?} Vertex Buffer", file_name)),
-                contents: bytemuck::cast_slice(&vertices),
-                usage: wgpu::BufferUsages::VERTEX,
-            });
-            let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some(&format!("{:?} Index Buffer", file_name)),
-                contents: bytemuck::cast_slice(&m.mesh.indices),
-                usage: wgpu::BufferUsages::INDEX,
-            });
-
-            model::Mesh {
-                name: file_name.to_string(),
-                vertex_buffer,
-                index_buffer,
-                num_elements: m.mesh.indices.len() as u32,
-                material: m.mesh.material_id.unwrap_or(0),
+        if let Some(camera) = node.camera() {
+            cameras.push(gltf_camera(&camera, &world));
+        }
+
+        if let Some(mesh) = node.mesh() {
+            let name = mesh.name().unwrap_or(file_name);
+            for primitive in mesh.primitives() {
+                if primitive.mode() != gltf::mesh::Mode::Triangles {
+                    log::warn!("{file_name}: skipping {:?} primitive", primitive.mode());
+                    continue;
+                }
+                let (vertices, indices) = gltf_primitive(&primitive, &buffers, &world);
+                let material = primitive.material().index().unwrap_or(default_material);
+                meshes.push(model::Mesh::new(
+                    device, name, &vertices, &indices, material,
+                ));
             }
+        }
+
+        nodes.extend(node.children().map(|child| (child, world)));
+    }
+
+    Ok(model::Model {
+        meshes,
+        materials,
+        cameras,
+        transform: Default::default(),
+    })
+}
+
+/// Read the vertices of a triangle primitive, transformed into model space.
+fn gltf_primitive(
+    primitive: &gltf::Primitive,
+    buffers: &[Vec<u8>],
+    world: &cgmath::Matrix4<f32>,
+) -> (Vec<model::ModelVertex>, Vec<u32>) {
+    let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+    let linear =
+        cgmath::Matrix3::from_cols(world.x.truncate(), world.y.truncate(), world.z.truncate());
+    let normal_matrix = linear.invert().unwrap_or(linear).transpose();
+
+    let mut vertices: Vec<model::ModelVertex> = reader
+        .read_positions()
+        .into_iter()
+        .flatten()
+        .map(|p| model::ModelVertex {
+            position: world.transform_point(p.into()).into(),
+            tex_coords: [0.0; 2],
+            normal: [0.0; 3],
+            tangent: [0.0; 3],
+            bitangent: [0.0; 3],
         })
-        .collect::<Vec<_>>();
+        .collect();
+    if let Some(normals) = reader.read_normals() {
+        for (vertex, normal) in vertices.iter_mut().zip(normals) {
+            vertex.normal = (normal_matrix * cgmath::Vector3::from(normal))
+                .normalize()
+                .into();
+        }
+    }
+    if let Some(tex_coords) = reader.read_tex_coords(0) {
+        for (vertex, tex_coords) in vertices.iter_mut().zip(tex_coords.into_f32()) {
+            vertex.tex_coords = tex_coords;
+        }
+    }
 
-    Ok(model::Model { meshes, materials })
+    let mut indices: Vec<u32> = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..vertices.len() as u32).collect(),
+    };
+    // A mirroring transform flips the winding order.
+    if linear.determinant() < 0.0 {
+        for triangle in indices.chunks_mut(3) {
+            triangle.reverse();
+        }
+    }
+
+    match reader.read_tangents() {
+        Some(tangents) => {
+            for (vertex, [x, y, z, w]) in vertices.iter_mut().zip(tangents) {
+                let tangent = (linear * cgmath::Vector3::new(x, y, z)).normalize();
+                let normal = cgmath::Vector3::from(vertex.normal);
+                vertex.tangent = tangent.into();
+                vertex.bitangent = (normal.cross(tangent) * w).into();
+            }
+        }
+        None => model::compute_tangents(&mut vertices, &indices),
+    }
+
+    (vertices, indices)
+}
+
+/// A camera node, looking down its local -Z axis.
+fn gltf_camera(camera: &gltf::Camera, world: &cgmath::Matrix4<f32>) -> model::ModelCamera {
+    let forward = -world.z.truncate().normalize();
+    model::ModelCamera {
+        position: cgmath::Point3::from_homogeneous(world.w),
+        yaw: cgmath::Rad(forward.z.atan2(forward.x)),
+        pitch: cgmath::Rad(forward.y.asin()),
+        fovy: match camera.projection() {
+            gltf::camera::Projection::Perspective(perspective) => {
+                Some(cgmath::Rad(perspective.yfov()))
+            }
+            gltf::camera::Projection::Orthographic(_) => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gltf_data_uri() {
+        let uri = "data:application/octet-stream;base64,AAECAw==";
+        let data = pollster::block_on(load_gltf_uri("model.gltf", uri)).unwrap();
+        assert_eq!(data, [0, 1, 2, 3]);
+
+        let plain = "data:text/plain,hello";
+        assert!(pollster::block_on(load_gltf_uri("model.gltf", plain)).is_err());
+        let invalid = "data:application/octet-stream;base64,!!!";
+        assert!(pollster::block_on(load_gltf_uri("model.gltf", invalid)).is_err());
+    }
 }
-*/
@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use crate::{
+    animation::Animation, draw_shape::DrawShape, instance::Instance, material::MaterialParams,
+    mesh::MeshData, object::Transform, resources::load_string, skybox::SkyboxSource,
+    texture::TextureOptions,
+};
+
+/// Scene description loaded from a RON file in `res/`.
+#[derive(Debug, Deserialize)]
+pub struct Scene {
+    /// Falls back to the first camera in `models`, then to the default camera.
+    #[serde(default)]
+    pub camera: Option<SceneCamera>,
+    /// Keeps the current skybox when missing.
+    #[serde(default)]
+    pub skybox: Option<SkyboxSource>,
+    #[serde(default)]
+    pub shapes: Vec<SceneShape>,
+    /// OBJ or glTF (`.gltf`, `.glb`) files in `res/`.
+    #[serde(default)]
+    pub models: Vec<String>,
+    /// Camera path file in `res/`, played as soon as the scene loads.
+    #[serde(default)]
+    pub camera_path: Option<String>,
+    /// Animations replacing those of the named parameters, e.g. `spin`,
+    /// `jitter_r` to `jitter_a` and `alpha`.
+    #[serde(default)]
+    pub params: BTreeMap<String, Animation>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SceneCamera {
+    pub position: [f32; 3],
+    /// Degrees.
+    pub yaw: f32,
+    /// Degrees.
+    pub pitch: f32,
+}
+
+impl Default for SceneCamera {
+    fn default() -> Self {
+        SceneCamera {
+            position: [0.0, 5.0, 10.0],
+            yaw: -90.0,
+            pitch: -20.0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SceneShape {
+    pub vertex_fn: String,
+    pub fragment_fn: String,
+    /// Ignored when `mesh` is given.
+    #[serde(default)]
+    pub vertex_count: u32,
+    /// Inline geometry, for vertex shaders that read a vertex buffer.
+    #[serde(default)]
+    pub mesh: Option<MeshData>,
+    #[serde(default)]
+    pub transform: Transform,
+    /// Texture file in `res/`, in place of the default texture.
+    #[serde(default)]
+    pub texture: Option<String>,
+    /// Texture file in `res/` multiplied with the emissive color.
+    #[serde(default)]
+    pub emissive_texture: Option<String>,
+    /// Sampling, color space and alpha of the textures. Shapes with the same
+    /// texture and options share it.
+    #[serde(default)]
+    pub texture_options: TextureOptions,
+    /// Tint, opacity, texture coordinate transform and emissive color.
+    #[serde(default)]
+    pub material: Option<MaterialParams>,
+    /// Copies of the shape drawn in one call, in place of a single one.
+    #[serde(default)]
+    pub instances: Option<Vec<Instance>>,
+    /// Occlude and be occluded by other shapes.
+    #[serde(default = "default_depth_test")]
+    pub depth_test: bool,
+}
+
+fn default_depth_test() -> bool {
+    true
+}
+
+impl SceneShape {
+    /// Drawn with a material of its own rather than the view's default one.
+    pub fn has_material(&self) -> bool {
+        self.texture.is_some() || self.emissive_texture.is_some() || self.material.is_some()
+    }
+
+    pub fn to_draw_shape(&self) -> DrawShape {
+        let mut shape = match &self.mesh {
+            Some(mesh) => DrawShape::with_mesh(&self.vertex_fn, &self.fragment_fn, mesh.to_mesh()),
+            None => DrawShape::new(&self.vertex_fn, &self.fragment_fn, self.vertex_count),
+        };
+        shape.transform = self.transform;
+        if let Some(instances) = &self.instances {
+            shape.instances = instances.clone();
+        }
+        if !self.depth_test {
+            shape.depth_compare = wgpu::CompareFunction::Always;
+            shape.depth_write = false;
+        }
+        shape
+    }
+}
+
+impl Scene {
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let options = ron::Options::default().with_default_extension(
+            ron::extensions::Extensions::IMPLICIT_SOME
+                | ron::extensions::Extensions::UNWRAP_VARIANT_NEWTYPES,
+        );
+        Ok(options.from_str(text)?)
+    }
+
+    pub async fn load(file_name: &str) -> anyhow::Result<Self> {
+        let text = load_string(file_name).await?;
+        Self::parse(&text).map_err(|e| anyhow::anyhow!("{}: {}", file_name, e))
+    }
+}
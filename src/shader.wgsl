@@ -5,18 +5,57 @@ struct VertexOutput {
 };
 
 struct Rotation {
-    transform: mat4x4<f32>,
     jitter: vec4<f32>,
+    angle: f32,
 };
 @group(0) @binding(0)
 var<uniform> r: Rotation;
 
+struct Object {
+    model: mat4x4<f32>,
+    normal: mat4x4<f32>,
+};
+@group(0) @binding(1)
+var<uniform> object: Object;
+
+struct InstanceInput {
+    @location(5) model_0: vec4<f32>,
+    @location(6) model_1: vec4<f32>,
+    @location(7) model_2: vec4<f32>,
+    @location(8) model_3: vec4<f32>,
+    @location(9) tint: vec4<f32>,
+    @location(10) phase: f32,
+};
+
+fn instance_model(instance: InstanceInput) -> mat4x4<f32> {
+    return object.model *
+        mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+}
+
+fn rotation_y(angle: f32) -> mat4x4<f32> {
+    let c = cos(angle);
+    let s = sin(angle);
+    return mat4x4<f32>(
+        vec4<f32>(c, 0.0, -s, 0.0),
+        vec4<f32>(0.0, 1.0, 0.0, 0.0),
+        vec4<f32>(s, 0.0, c, 0.0),
+        vec4<f32>(0.0, 0.0, 0.0, 1.0),
+    );
+}
+
+// The shared spin, advanced by the instance phase.
+fn instance_spin(instance: InstanceInput) -> mat4x4<f32> {
+    return rotation_y(r.angle + instance.phase);
+}
+
 struct Camera {
     view_position: vec4<f32>,
     view_proj: mat4x4<f32>,
     proj: mat4x4<f32>,
     proj_inv: mat4x4<f32>,
     view: mat4x4<f32>,
+    // 1.0, or 0.0 for reverse-Z projections.
+    far_depth: f32,
 };
 @group(1) @binding(0)
 var<uniform> camera: Camera;
@@ -24,6 +63,7 @@ var<uniform> camera: Camera;
 @vertex
 fn vs_background(
     @builtin(vertex_index) in_vertex_index: u32,
+    instance: InstanceInput,
 ) -> VertexOutput {
     var vertices = array<vec3<f32>, 6>(
         vec3<f32>(-10.0, 10.0, -10.0),
@@ -56,9 +96,10 @@ fn vs_background(
 
     out.clip_position =
         camera.view_proj *
+        instance_model(instance) *
         vec4<f32>(v, 1.0) +
-        vec4<f32>(4.0 + r.jitter.x * 0.2, r.jitter.y * 0.15, 0.0, 0.0);
-    out.color = colors[in_vertex_index];
+        vec4<f32>(r.jitter.x * 0.2, r.jitter.y * 0.15, 0.0, 0.0);
+    out.color = colors[in_vertex_index] * instance.tint;
     out.tex_coords = coords[in_vertex_index];
 
     return out;
@@ -67,6 +108,7 @@ fn vs_background(
 @vertex
 fn vs_pyramid(
     @builtin(vertex_index) in_vertex_index: u32,
+    instance: InstanceInput,
 ) -> VertexOutput {
     var vertices = array<vec3<f32>, 9>(
         vec3<f32>(0.0, 0.5, 0.0),
@@ -100,8 +142,8 @@ fn vs_pyramid(
 
     var out: VertexOutput;
     out.clip_position =
-        camera.view_proj * r.transform * vec4<f32>(v, 1.0) + vec4<f32>(4.0, 0.0, 0.0, 0.0);
-    out.color = colors[in_vertex_index];
+        camera.view_proj * instance_model(instance) * instance_spin(instance) * vec4<f32>(v, 1.0);
+    out.color = colors[in_vertex_index] * instance.tint;
     out.tex_coords = vec2<f32>(0.0, 0.0);
     return out;
 }
@@ -109,6 +151,7 @@ fn vs_pyramid(
 @vertex
 fn vs_pyramid4(
     @builtin(vertex_index) in_vertex_index: u32,
+    instance: InstanceInput,
 ) -> VertexOutput {
     var vertices = array<vec3<f32>, 12>(
         vec3<f32>(0.0, 0.5, 0.0),
@@ -150,12 +193,28 @@ fn vs_pyramid4(
 
     var out: VertexOutput;
     out.clip_position =
-        camera.view_proj * r.transform * vec4<f32>(v, 1.0) + vec4<f32>(-4.0, 0.0, 0.0, 0.0);
-    out.color = colors[in_vertex_index];
+        camera.view_proj * instance_model(instance) * instance_spin(instance) * vec4<f32>(v, 1.0);
+    out.color = colors[in_vertex_index] * instance.tint;
     out.tex_coords = vec2<f32>(0.0, 0.0);
     return out;
 }
 
+struct MeshInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_mesh(in: MeshInput, instance: InstanceInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position =
+        camera.view_proj * instance_model(instance) * instance_spin(instance) * vec4<f32>(in.position, 1.0);
+    out.color = in.color * instance.tint;
+    out.tex_coords = in.tex_coords;
+    return out;
+}
+
 struct SkyOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) uv: vec3<f32>,
@@ -163,22 +222,27 @@ struct SkyOutput {
 
 @vertex
 fn vs_sky(@builtin(vertex_index) vertex_index: u32) -> SkyOutput {
-    // hacky way to draw a large triangle
+    // hacky way to draw a large triangle, landing on the far plane
     let tmp1 = i32(vertex_index) / 2;
     let tmp2 = i32(vertex_index) & 1;
     let pos = vec4<f32>(
         f32(tmp1) * 4.0 - 1.0,
         f32(tmp2) * 4.0 - 1.0,
-        1.0,
+        camera.far_depth,
         1.0
     );
 
     // transposition = inversion for this orthonormal matrix
     let inv_model_view = transpose(mat3x3<f32>(camera.view.x.xyz, camera.view.y.xyz, camera.view.z.xyz));
-    let unprojected = camera.proj_inv * pos;
+    // The view ray runs from the near plane to the far plane. Subtracting in
+    // homogeneous coordinates keeps a far plane at infinity (w = 0) finite,
+    // and gives parallel rays for an orthographic projection.
+    let near = camera.proj_inv * vec4<f32>(pos.xy, 1.0 - camera.far_depth, 1.0);
+    let far = camera.proj_inv * pos;
+    let ray = far.xyz * near.w - near.xyz * far.w;
 
     var result: SkyOutput;
-    result.uv = inv_model_view * unprojected.xyz;
+    result.uv = inv_model_view * ray;
     result.position = pos;
     return result;
 }
@@ -189,8 +253,14 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
 }
 
 
-struct Raw {
-    data: vec4<f32>,
+struct Material {
+    // rgb tint and opacity
+    color: vec4<f32>,
+    // rgb emissive, and w 1 when the base texture has premultiplied alpha
+    emissive: vec4<f32>,
+    // Rows of the 2x3 texture coordinate transform
+    uv_x: vec4<f32>,
+    uv_y: vec4<f32>,
 }
 
 @group(3) @binding(0)
@@ -198,11 +268,22 @@ var t_diffuse: texture_2d<f32>;
 @group(3) @binding(1)
 var s_diffuse: sampler;
 @group(3) @binding(2)
-var<uniform> alpha: Raw;
+var<uniform> material: Material;
+@group(3) @binding(3)
+var t_emissive: texture_2d<f32>;
+@group(3) @binding(4)
+var s_emissive: sampler;
 
 @fragment
 fn fs_texture(in: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(textureSample(t_diffuse, s_diffuse, in.tex_coords).xyz, alpha.data.w);
+    let uv = vec3<f32>(in.tex_coords, 1.0);
+    let tex_coords = vec2<f32>(dot(material.uv_x.xyz, uv), dot(material.uv_y.xyz, uv));
+    let base = textureSample(t_diffuse, s_diffuse, tex_coords);
+    let emissive = textureSample(t_emissive, s_emissive, tex_coords).rgb * material.emissive.rgb;
+    let color = base.rgb * material.color.rgb + emissive;
+    // Premultiplied color fades with opacity along with alpha.
+    let fade = mix(1.0, material.color.a, material.emissive.w);
+    return vec4<f32>(color * fade, base.a * material.color.a);
 }
 
 @group(2) @binding(0)
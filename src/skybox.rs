@@ -1,73 +1,349 @@
+use image::{imageops, RgbaImage};
+use serde::{Deserialize, Serialize};
 use wgpu::util::DeviceExt;
 
+use crate::{
+    cube_face,
+    mipmap::{self, MipmapGenerator},
+    resources::load_binary,
+    starfield::Starfield,
+};
+
+/// Face names in cube layer order.
+const FACES: [&str; 6] = ["right", "left", "top", "bottom", "front", "back"];
+
+/// Where the skybox images come from. Files are in `res/`, or absolute
+/// paths on native.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SkyboxSource {
+    /// One file per face, in the order right, left, top, bottom, front, back.
+    Faces([String; 6]),
+    /// A single image with the faces laid out as a horizontal (4x3) or
+    /// vertical (3x4) cross, or as a horizontal (6x1) or vertical (1x6)
+    /// strip in face order.
+    Image(String),
+    /// An equirectangular panorama, LDR or Radiance `.hdr`, projected onto
+    /// cube faces of `size` pixels, a quarter of its width by default.
+    Equirect {
+        file: String,
+        #[serde(default)]
+        size: Option<u32>,
+    },
+    /// Stars and nebulae generated from seeded parameters.
+    Starfield(Starfield),
+}
+
+impl Default for SkyboxSource {
+    fn default() -> Self {
+        SkyboxSource::Faces(FACES.map(|face| format!("cmb/cmb_{}.png", face)))
+    }
+}
+
+impl SkyboxSource {
+    /// Read the files of the source, ahead of creating the skybox on the GPU.
+    pub async fn fetch(self) -> anyhow::Result<FetchedSkybox> {
+        let file_names: Vec<&String> = match &self {
+            SkyboxSource::Faces(file_names) => file_names.iter().collect(),
+            SkyboxSource::Image(file_name) => vec![file_name],
+            SkyboxSource::Equirect { file, .. } => vec![file],
+            SkyboxSource::Starfield(_) => Vec::new(),
+        };
+        let mut files = Vec::new();
+        for file_name in file_names {
+            files.push(load_binary(file_name).await?);
+        }
+        Ok(FetchedSkybox {
+            source: self,
+            files,
+        })
+    }
+}
+
+/// A skybox source with its files read.
+pub struct FetchedSkybox {
+    pub source: SkyboxSource,
+    files: Vec<Vec<u8>>,
+}
+
 pub struct Skybox {
     pub bind_group_layout: wgpu::BindGroupLayout,
     pub bind_group: wgpu::BindGroup,
 }
 
 impl Skybox {
-    pub fn new(
+    pub async fn load(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-    ) -> Self {
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            label: None,
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Linear,
-            ..Default::default()
-        });
-        let size = wgpu::Extent3d {
-            //width: 2048,
-            //height: 2048,
-            width: 200,
-            height: 200,
-            depth_or_array_layers: 6,
-        };
+        mipmaps: &MipmapGenerator,
+        source: &SkyboxSource,
+    ) -> anyhow::Result<Self> {
+        Self::from_fetched(device, queue, mipmaps, &source.clone().fetch().await?)
+    }
+
+    pub fn from_fetched(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+        fetched: &FetchedSkybox,
+    ) -> anyhow::Result<Self> {
+        let files = &fetched.files;
+        match &fetched.source {
+            SkyboxSource::Faces(_) => {
+                let faces: [&[u8]; 6] = std::array::from_fn(|i| files[i].as_slice());
+                Self::from_faces(device, queue, mipmaps, faces)
+            }
+            SkyboxSource::Image(file_name) => Self::from_layout(device, queue, mipmaps, &files[0])
+                .map_err(|e| anyhow::anyhow!("{}: {}", file_name, e)),
+            SkyboxSource::Equirect { file, size } => {
+                Self::from_equirect(device, queue, mipmaps, &files[0], *size)
+                    .map_err(|e| anyhow::anyhow!("{}: {}", file, e))
+            }
+            SkyboxSource::Starfield(starfield) => Ok(Self::from_texture(
+                device,
+                &starfield.render(device, queue, mipmaps)?,
+            )),
+        }
+    }
 
-        fn pack_skybox(accum: &mut Vec<u8>, image_bytes: &[u8]) {
-            let decoded = image::load_from_memory(image_bytes).unwrap().into_rgba8();
-            accum.extend_from_slice(decoded.as_raw());
+    /// Create a skybox from six encoded images in the order right, left,
+    /// top, bottom, front, back.
+    pub fn from_faces(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+        faces: [&[u8]; 6],
+    ) -> anyhow::Result<Self> {
+        let mut images = Vec::new();
+        for (face, bytes) in FACES.iter().zip(faces) {
+            let image = image::load_from_memory(bytes)
+                .map_err(|e| anyhow::anyhow!("Skybox {} face: {}", face, e))?;
+            images.push(image.into_rgba8());
         }
-        let mut data = Vec::new();
-        //pack_skybox(&mut data, include_bytes!("../res/skybox/right.jpg"));
-        //pack_skybox(&mut data, include_bytes!("../res/skybox/left.jpg"));
-        //pack_skybox(&mut data, include_bytes!("../res/skybox/top.jpg"));
-        //pack_skybox(&mut data, include_bytes!("../res/skybox/bottom.jpg"));
-        //pack_skybox(&mut data, include_bytes!("../res/skybox/front.jpg"));
-        //pack_skybox(&mut data, include_bytes!("../res/skybox/back.jpg"));
-        pack_skybox(&mut data, include_bytes!("../res/cmb/cmb_right.png"));
-        pack_skybox(&mut data, include_bytes!("../res/cmb/cmb_left.png"));
-        pack_skybox(&mut data, include_bytes!("../res/cmb/cmb_top.png"));
-        pack_skybox(&mut data, include_bytes!("../res/cmb/cmb_bottom.png"));
-        pack_skybox(&mut data, include_bytes!("../res/cmb/cmb_front.png"));
-        pack_skybox(&mut data, include_bytes!("../res/cmb/cmb_back.png"));
-
-        let texture = device.create_texture_with_data(
+        let images: [RgbaImage; 6] = images.try_into().unwrap();
+        Self::from_images(device, queue, mipmaps, &images)
+    }
+
+    /// Create a skybox from one encoded image holding all faces as a cross
+    /// or strip, told apart by aspect ratio.
+    pub fn from_layout(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+        bytes: &[u8],
+    ) -> anyhow::Result<Self> {
+        let image = image::load_from_memory(bytes)?.into_rgba8();
+        Self::from_images(device, queue, mipmaps, &split_layout(&image)?)
+    }
+
+    /// Create a skybox from one encoded equirectangular panorama, projecting
+    /// it on the GPU onto faces of `size` pixels. Radiance `.hdr` images keep
+    /// their range in a float cube texture.
+    pub fn from_equirect(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+        bytes: &[u8],
+        size: Option<u32>,
+    ) -> anyhow::Result<Self> {
+        // Radiance files only decode to 8 bits through `DynamicImage`, so
+        // their floats are read directly and packed into a shared exponent, a
+        // quarter of the size of full floats and still filterable.
+        let (width, height, format, data) =
+            if image::guess_format(bytes)? == image::ImageFormat::Hdr {
+                let decoder = image::codecs::hdr::HdrDecoder::new(bytes)?;
+                let meta = decoder.metadata();
+                let texels: Vec<u32> = decoder
+                    .read_image_hdr()?
+                    .iter()
+                    .map(|pixel| rgb9e5(pixel.0))
+                    .collect();
+                (
+                    meta.width,
+                    meta.height,
+                    wgpu::TextureFormat::Rgb9e5Ufloat,
+                    bytemuck::cast_slice(&texels).to_vec(),
+                )
+            } else {
+                let image = image::load_from_memory(bytes)?.into_rgba8();
+                (
+                    image.width(),
+                    image.height(),
+                    wgpu::TextureFormat::Rgba8UnormSrgb,
+                    image.into_raw(),
+                )
+            };
+        if width == 0 || width != height * 2 {
+            anyhow::bail!("{}x{} is not a 2:1 equirectangular panorama", width, height);
+        }
+        let size = size.unwrap_or(width / 4).max(1);
+        let limit = device.limits().max_texture_dimension_2d;
+        if width > limit || size > limit {
+            anyhow::bail!(
+                "Panorama is {} and faces {} pixels, more than the {} supported",
+                width,
+                size,
+                limit
+            );
+        }
+
+        let panorama = device.create_texture_with_data(
             queue,
             &wgpu::TextureDescriptor {
-                size,
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
                 mip_level_count: 1,
                 sample_count: 1,
                 dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                format,
                 usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-                label: None,
+                label: Some("Panorama"),
                 view_formats: &[],
             },
             &data,
         );
 
+        let cube = cube_face::create_target(device, size, "Skybox");
+        project_equirect(device, queue, &panorama, &cube, size);
+        mipmaps.generate(device, queue, &cube);
+        Ok(Self::from_texture(device, &cube))
+    }
+
+    /// A skybox of one color, for when no images can be loaded.
+    pub fn solid(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+        color: [u8; 4],
+    ) -> Self {
+        let face = RgbaImage::from_pixel(1, 1, image::Rgba(color));
+        Self::from_images(
+            device,
+            queue,
+            mipmaps,
+            &std::array::from_fn(|_| face.clone()),
+        )
+        .unwrap()
+    }
+
+    /// Create a skybox from six decoded faces in cube layer order. The faces
+    /// must be square and all the same size.
+    pub fn from_images(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+        faces: &[RgbaImage; 6],
+    ) -> anyhow::Result<Self> {
+        let (width, height) = faces[0].dimensions();
+        if width == 0 || width != height {
+            anyhow::bail!("Skybox faces must be square, not {}x{}", width, height);
+        }
+        let limit = device.limits().max_texture_dimension_2d;
+        if width > limit {
+            anyhow::bail!(
+                "Skybox faces are {} pixels, more than the {} supported",
+                width,
+                limit
+            );
+        }
+        let mut data = Vec::new();
+        for (name, face) in FACES.iter().zip(faces) {
+            if face.dimensions() != (width, height) {
+                let (w, h) = face.dimensions();
+                anyhow::bail!(
+                    "Skybox {} face is {}x{}, the {} face {}x{}",
+                    name,
+                    w,
+                    h,
+                    FACES[0],
+                    width,
+                    height
+                );
+            }
+            data.extend_from_slice(face.as_raw());
+        }
+
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 6,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size,
+            mip_level_count: mipmap::level_count(width, height),
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            label: Some("Skybox"),
+            view_formats: &[],
+        });
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            size,
+        );
+        mipmaps.generate(device, queue, &texture);
+        Ok(Self::from_texture(device, &texture))
+    }
+
+    /// Bind a cube texture with six layers.
+    pub fn from_texture(device: &wgpu::Device, texture: &wgpu::Texture) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: None,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
         let texture_view = texture.create_view(&wgpu::TextureViewDescriptor {
             label: None,
             dimension: Some(wgpu::TextureViewDimension::Cube),
             ..wgpu::TextureViewDescriptor::default()
         });
 
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        let bind_group_layout = Self::bind_group_layout(device);
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        Skybox {
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: None,
             entries: &[
                 wgpu::BindGroupLayoutEntry {
@@ -87,26 +363,250 @@ impl Skybox {
                     count: None,
                 },
             ],
-        });
+        })
+    }
+}
+
+/// Render each layer of `cube` from the equirectangular `panorama`.
+fn project_equirect(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    panorama: &wgpu::Texture,
+    cube: &wgpu::Texture,
+    size: u32,
+) {
+    let shader = cube_face::shader(device, "Equirect", include_str!("equirect.wgsl"));
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Equirect"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+            cube_face::uniform_layout_entry(2),
+        ],
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Equirect"),
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Equirect"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_face",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_face",
+            targets: &[Some(cube.format().into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    });
+
+    // Longitude wraps around, latitude stops at the poles.
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        label: None,
+        address_mode_u: wgpu::AddressMode::Repeat,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    });
+    let panorama_view = panorama.create_view(&wgpu::TextureViewDescriptor::default());
 
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Equirect"),
+    });
+    for index in 0..6 {
+        let uniform = cube_face::uniform_buffer(device, index, size);
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
+            label: Some("Equirect"),
             layout: &bind_group_layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                    resource: wgpu::BindingResource::TextureView(&panorama_view),
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
                     resource: wgpu::BindingResource::Sampler(&sampler),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform.as_entire_binding(),
+                },
             ],
         });
+        let view = cube_face::layer_view(cube, index);
 
-        Skybox {
-            bind_group_layout,
-            bind_group,
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Equirect"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+    queue.submit(std::iter::once(encoder.finish()));
+}
+
+/// Pack linear RGB into `Rgb9e5Ufloat`: three 9 bit mantissas sharing a
+/// 5 bit exponent, red in the low bits.
+fn rgb9e5(rgb: [f32; 3]) -> u32 {
+    const MANTISSA_BITS: i32 = 9;
+    const EXPONENT_BIAS: i32 = 15;
+    const MAX_VALUE: f32 = 65408.0;
+
+    let rgb = rgb.map(|c| if c > 0.0 { c.min(MAX_VALUE) } else { 0.0 });
+    let max = rgb[0].max(rgb[1]).max(rgb[2]);
+    let mut exponent = (max.log2().floor() as i32).max(-EXPONENT_BIAS - 1) + 1 + EXPONENT_BIAS;
+    let mut scale = 2f32.powi(exponent - EXPONENT_BIAS - MANTISSA_BITS);
+    // Rounding can carry the largest mantissa into the next exponent.
+    if (max / scale).round() as i32 == 1 << MANTISSA_BITS {
+        exponent += 1;
+        scale *= 2.0;
+    }
+    let [r, g, b] = rgb.map(|c| (c / scale).round() as u32);
+    r | g << 9 | b << 18 | (exponent as u32) << 27
+}
+
+/// Cut a cross or strip layout into faces in cube layer order.
+fn split_layout(image: &RgbaImage) -> anyhow::Result<[RgbaImage; 6]> {
+    let (width, height) = image.dimensions();
+    // Cell of each face, in cube layer order.
+    let (columns, rows, cells): (u32, u32, [(u32, u32); 6]) = if width * 3 == height * 4 {
+        (4, 3, [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)])
+    } else if width * 4 == height * 3 {
+        (3, 4, [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (1, 3)])
+    } else if width == height * 6 {
+        (6, 1, [(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0)])
+    } else if width * 6 == height {
+        (1, 6, [(0, 0), (0, 1), (0, 2), (0, 3), (0, 4), (0, 5)])
+    } else {
+        anyhow::bail!(
+            "{}x{} is not a 4x3 or 3x4 cross or a 6x1 or 1x6 strip of square faces",
+            width,
+            height
+        );
+    };
+    let size = width / columns;
+    if size * columns != width || size * rows != height {
+        anyhow::bail!("{}x{} doesn't divide into square faces", width, height);
+    }
+
+    let faces = cells.map(|(column, row)| {
+        imageops::crop_imm(image, column * size, row * size, size, size).to_image()
+    });
+    // The back face of a vertical cross hangs upside down below the bottom.
+    let [right, left, top, bottom, front, back] = faces;
+    let back = if rows == 4 {
+        imageops::rotate180(&back)
+    } else {
+        back
+    };
+    Ok([right, left, top, bottom, front, back])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unpack_rgb9e5(texel: u32) -> [f32; 3] {
+        let scale = 2f32.powi((texel >> 27) as i32 - 15 - 9);
+        [0, 9, 18].map(|shift| ((texel >> shift) & 0x1ff) as f32 * scale)
+    }
+
+    #[test]
+    fn rgb9e5_zero() {
+        assert_eq!(rgb9e5([0.0; 3]), 0);
+        assert_eq!(rgb9e5([-1.0, f32::NAN, 0.0]), 0);
+    }
+
+    #[test]
+    fn rgb9e5_max() {
+        let max = rgb9e5([65408.0; 3]);
+        assert_eq!(unpack_rgb9e5(max), [65408.0; 3]);
+        assert_eq!(rgb9e5([1e9, f32::INFINITY, 65408.0]), max);
+    }
+
+    #[test]
+    fn rgb9e5_rounding_carry() {
+        // Just under one rounds the mantissa up to 512, which only fits in
+        // the next exponent.
+        let texel = rgb9e5([0.9999, 0.5, 0.0]);
+        assert_eq!(texel >> 27, 16);
+        assert_eq!(unpack_rgb9e5(texel), [1.0, 0.5, 0.0]);
+    }
+
+    /// Faces of 2 pixels, each pixel holding its cell and its spot in it.
+    fn layout(columns: u32, rows: u32) -> RgbaImage {
+        RgbaImage::from_fn(columns * 2, rows * 2, |x, y| {
+            image::Rgba([(x / 2) as u8, (y / 2) as u8, (x % 2) as u8, (y % 2) as u8])
+        })
+    }
+
+    fn check_cells(columns: u32, rows: u32, cells: [(u8, u8); 6]) -> [RgbaImage; 6] {
+        let faces = split_layout(&layout(columns, rows)).unwrap();
+        for (face, (column, row)) in faces.iter().zip(cells) {
+            assert_eq!(face.dimensions(), (2, 2));
+            let [c, r, _, _] = face.get_pixel(1, 0).0;
+            assert_eq!((c, r), (column, row));
         }
+        faces
+    }
+
+    #[test]
+    fn split_horizontal_cross() {
+        let faces = check_cells(4, 3, [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)]);
+        assert_eq!(faces[5].get_pixel(0, 0).0, [3, 1, 0, 0]);
+    }
+
+    #[test]
+    fn split_vertical_cross() {
+        // The back face is turned upright.
+        let faces = check_cells(3, 4, [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (1, 3)]);
+        assert_eq!(faces[5].get_pixel(0, 0).0, [1, 3, 1, 1]);
+    }
+
+    #[test]
+    fn split_horizontal_strip() {
+        check_cells(6, 1, [(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0)]);
+    }
+
+    #[test]
+    fn split_vertical_strip() {
+        check_cells(1, 6, [(0, 0), (0, 1), (0, 2), (0, 3), (0, 4), (0, 5)]);
+    }
+
+    #[test]
+    fn split_other_aspect() {
+        assert!(split_layout(&layout(5, 3)).is_err());
     }
 }
@@ -0,0 +1,448 @@
+use noize::{Ease, PNoise1};
+use serde::{Deserialize, Serialize};
+use wgpu::util::DeviceExt;
+
+use crate::{cube_face, mipmap::MipmapGenerator, texture};
+
+/// Nebula layers the shader has room for.
+pub const MAX_NEBULAE: usize = 4;
+
+/// Noise values per nebula layer that the shader's lattice points pick from.
+const LATTICE_SIZE: usize = 256;
+
+/// Seeded description of a procedural sky: stars over clouds of noise. The
+/// same parameters always give the same sky, at any resolution.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Starfield {
+    pub seed: u32,
+    /// Face resolution in pixels.
+    pub size: u32,
+    /// Stars over the whole sky.
+    pub stars: u32,
+    /// Apparent magnitudes of the brightest and faintest stars.
+    pub magnitudes: (f32, f32),
+    /// How many times more stars each fainter magnitude has, about 2.5 for
+    /// the night sky.
+    pub magnitude_growth: f32,
+    /// Glow radius of the brightest stars, in degrees. Fainter stars are
+    /// smaller, keeping the same peak brightness.
+    pub star_size: f32,
+    pub brightness: f32,
+    /// Range of star colour temperatures, in kelvin.
+    pub temperatures: (f32, f32),
+    /// Linear color behind stars and nebulae.
+    pub background: [f32; 3],
+    /// At most `MAX_NEBULAE` layers, added together.
+    pub nebulae: Vec<NebulaLayer>,
+}
+
+impl Default for Starfield {
+    fn default() -> Self {
+        Starfield {
+            seed: 0,
+            size: 1024,
+            stars: 6000,
+            magnitudes: (-1.0, 7.0),
+            magnitude_growth: 2.5,
+            star_size: 0.1,
+            brightness: 2.0,
+            temperatures: (3000.0, 12000.0),
+            background: [0.0, 0.0, 0.004],
+            nebulae: vec![
+                NebulaLayer {
+                    color: [0.5, 0.15, 0.8],
+                    intensity: 0.25,
+                    frequency: 1.5,
+                    octaves: 5,
+                    coverage: 0.45,
+                },
+                NebulaLayer {
+                    color: [0.1, 0.45, 0.55],
+                    intensity: 0.15,
+                    frequency: 3.0,
+                    octaves: 4,
+                    coverage: 0.55,
+                },
+            ],
+        }
+    }
+}
+
+/// Clouds where fractal value noise over directions rises above `coverage`.
+/// Each layer's lattice values come from its own `noize` sequence.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NebulaLayer {
+    /// Linear color.
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// Noise cells per unit of direction in the first octave.
+    pub frequency: f32,
+    pub octaves: u32,
+    /// Share of the noise range left clear, from 0 to 1.
+    pub coverage: f32,
+}
+
+impl Default for NebulaLayer {
+    fn default() -> Self {
+        NebulaLayer {
+            color: [0.5, 0.5, 0.5],
+            intensity: 0.2,
+            frequency: 2.0,
+            octaves: 4,
+            coverage: 0.5,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct NebulaLayerUniform {
+    color: [f32; 4],
+    frequency: f32,
+    octaves: u32,
+    coverage: f32,
+    seed: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct NebulaUniform {
+    background: [f32; 4],
+    layers: [NebulaLayerUniform; MAX_NEBULAE],
+    count: u32,
+    _padding: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct Star {
+    direction: [f32; 3],
+    /// Standard deviation of the glow, in face coordinates.
+    radius: f32,
+    color: [f32; 3],
+}
+
+impl Star {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32, 2 => Float32x3];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Star>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+impl Starfield {
+    /// Render the sky into a new cube texture.
+    pub fn render(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+    ) -> anyhow::Result<wgpu::Texture> {
+        if self.nebulae.len() > MAX_NEBULAE {
+            anyhow::bail!(
+                "Starfield has {} nebula layers, at most {} are supported",
+                self.nebulae.len(),
+                MAX_NEBULAE
+            );
+        }
+        let size = self.size.max(1);
+        let limit = device.limits().max_texture_dimension_2d;
+        if size > limit {
+            anyhow::bail!(
+                "Starfield faces are {} pixels, more than the {} supported",
+                size,
+                limit
+            );
+        }
+
+        let shader = cube_face::shader(device, "Starfield", include_str!("starfield.wgsl"));
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Starfield"),
+            entries: &[
+                cube_face::uniform_layout_entry(0),
+                cube_face::uniform_layout_entry(1),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Starfield"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let nebula_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Nebula"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_face",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_nebula",
+                targets: &[Some(cube_face::FORMAT.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        let additive = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let star_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Stars"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_star",
+                buffers: &[Star::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_star",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: cube_face::FORMAT,
+                    blend: Some(wgpu::BlendState {
+                        color: additive,
+                        alpha: additive,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let nebula = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Nebula"),
+            contents: bytemuck::cast_slice(&[self.nebula_uniform()]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let lattice = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("Nebula lattice"),
+                size: wgpu::Extent3d {
+                    width: LATTICE_SIZE as u32,
+                    height: MAX_NEBULAE as u32,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::R32Float,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            bytemuck::cast_slice(&self.lattice_values()),
+        );
+        let lattice_view = lattice.create_view(&wgpu::TextureViewDescriptor::default());
+        let stars = self.stars();
+        let star_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Stars"),
+            contents: bytemuck::cast_slice(&stars),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let cube = cube_face::create_target(device, size, "Starfield");
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Starfield"),
+        });
+        for index in 0..6 {
+            let uniform = cube_face::uniform_buffer(device, index, size);
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Starfield"),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniform.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: nebula.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(&lattice_view),
+                    },
+                ],
+            });
+            let view = cube_face::layer_view(&cube, index);
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Starfield"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.set_pipeline(&nebula_pipeline);
+            render_pass.draw(0..3, 0..1);
+            if !stars.is_empty() {
+                render_pass.set_pipeline(&star_pipeline);
+                render_pass.set_vertex_buffer(0, star_buffer.slice(..));
+                render_pass.draw(0..4, 0..stars.len() as u32);
+            }
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+        mipmaps.generate(device, queue, &cube);
+        Ok(cube)
+    }
+
+    fn nebula_uniform(&self) -> NebulaUniform {
+        let mut layers = [NebulaLayerUniform::default(); MAX_NEBULAE];
+        for (index, (uniform, layer)) in layers.iter_mut().zip(&self.nebulae).enumerate() {
+            let [r, g, b] = layer.color;
+            *uniform = NebulaLayerUniform {
+                color: [r, g, b, layer.intensity],
+                frequency: layer.frequency,
+                octaves: layer.octaves,
+                coverage: layer.coverage,
+                seed: self.seed ^ (index as u32 + 1).wrapping_mul(0x9e37_79b9),
+            };
+        }
+        let [r, g, b] = self.background;
+        NebulaUniform {
+            background: [r, g, b, 1.0],
+            layers,
+            count: self.nebulae.len() as u32,
+            _padding: [0; 3],
+        }
+    }
+
+    /// One row of `LATTICE_SIZE` noise values per nebula layer, rescaled to
+    /// 0..1.
+    fn lattice_values(&self) -> Vec<f32> {
+        let mut values = vec![0.0; LATTICE_SIZE * MAX_NEBULAE];
+        for (index, row) in values
+            .chunks_mut(LATTICE_SIZE)
+            .take(self.nebulae.len())
+            .enumerate()
+        {
+            let seed = self.seed.wrapping_add(index as u32);
+            let noise = PNoise1::new(seed as _, 4, LATTICE_SIZE, Ease::SmoothStep);
+            for (value, sample) in row.iter_mut().zip(noise) {
+                *value = sample;
+            }
+            let (min, max) = row.iter().fold((f32::MAX, f32::MIN), |(min, max), &v| {
+                (min.min(v), max.max(v))
+            });
+            let range = (max - min).max(f32::EPSILON);
+            for value in row.iter_mut() {
+                *value = (*value - min) / range;
+            }
+        }
+        values
+    }
+
+    /// Scatter the stars evenly over the sphere, with more of them at each
+    /// fainter magnitude.
+    fn stars(&self) -> Vec<Star> {
+        let mut rng = Rng::new(self.seed);
+        let (brightest, faintest) = self.magnitudes;
+        let range = faintest - brightest;
+        let growth = self.magnitude_growth.max(1.0);
+        let (coolest, hottest) = self.temperatures;
+        let radius = self.star_size.to_radians().tan();
+
+        (0..self.stars)
+            .map(|_| {
+                let z = rng.next_f32() * 2.0 - 1.0;
+                let angle = rng.next_f32() * std::f32::consts::TAU;
+                let ring = (1.0 - z * z).sqrt();
+                let direction = [ring * angle.cos(), ring * angle.sin(), z];
+
+                // Invert the cumulative count, which grows by `growth` per
+                // magnitude.
+                let u = rng.next_f32();
+                let magnitude = if growth > 1.0 {
+                    brightest + (1.0 + u * (growth.powf(range) - 1.0)).ln() / growth.ln()
+                } else {
+                    brightest + u * range
+                };
+                let flux = 10f32.powf(-0.4 * (magnitude - brightest));
+
+                let temperature = coolest + rng.next_f32() * (hottest - coolest);
+                Star {
+                    direction,
+                    radius: radius * flux.sqrt(),
+                    color: blackbody(temperature).map(|c| c * self.brightness),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Linear RGB of a star at `kelvin`, brightest channel at one. Tanner
+/// Helland's fit of the blackbody colors.
+fn blackbody(kelvin: f32) -> [f32; 3] {
+    let t = (kelvin / 100.0).clamp(10.0, 400.0);
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.698_73 * (t - 60.0).powf(-0.133_204_76)
+    };
+    let green = if t <= 66.0 {
+        99.470_8 * t.ln() - 161.119_57
+    } else {
+        288.122_17 * (t - 60.0).powf(-0.075_514_85)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (t - 10.0).ln() - 305.044_8
+    };
+    [red, green, blue].map(|c| texture::srgb_to_linear((c / 255.0).clamp(0.0, 1.0)))
+}
+
+/// SplitMix64, so a seed always scatters the same stars.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u32) -> Self {
+        Rng(seed as u64)
+    }
+
+    /// Uniform in `0.0..1.0`.
+    fn next_f32(&mut self) -> f32 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
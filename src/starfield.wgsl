@@ -0,0 +1,118 @@
+// Procedural sky, drawn into one cube face per pass: nebula clouds over the
+// background, then stars added on top.
+
+struct NebulaLayer {
+    // rgb and intensity
+    color: vec4<f32>,
+    frequency: f32,
+    octaves: u32,
+    coverage: f32,
+    seed: u32,
+};
+
+struct Nebula {
+    background: vec4<f32>,
+    layers: array<NebulaLayer, 4>,
+    count: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> face: Face;
+@group(0) @binding(1)
+var<uniform> nebula: Nebula;
+// One row of noise values per layer, picked by hashing lattice points
+@group(0) @binding(2)
+var lattice_table: texture_2d<f32>;
+
+// 3D value noise over directions, so the sky has no seams between faces.
+
+// Murmur3 finalizer over a lattice point.
+fn hash(cell: vec3<i32>, seed: u32) -> u32 {
+    let c = bitcast<vec3<u32>>(cell);
+    var h = seed ^ (c.x * 0x8da6b343u) ^ (c.y * 0xd8163841u) ^ (c.z * 0xcb1ab31fu);
+    h = h ^ (h >> 16u);
+    h = h * 0x85ebca6bu;
+    h = h ^ (h >> 13u);
+    h = h * 0xc2b2ae35u;
+    h = h ^ (h >> 16u);
+    return h;
+}
+
+fn lattice(cell: vec3<i32>, layer: u32, seed: u32) -> f32 {
+    let index = hash(cell, seed) % u32(textureDimensions(lattice_table).x);
+    return textureLoad(lattice_table, vec2<u32>(index, layer), 0).r;
+}
+
+// Smoothly interpolated lattice values, in 0..1.
+fn value_noise(p: vec3<f32>, layer: u32, seed: u32) -> f32 {
+    let cell = vec3<i32>(floor(p));
+    let f = fract(p);
+    let u = f * f * (3.0 - 2.0 * f);
+    let x00 = mix(lattice(cell, layer, seed), lattice(cell + vec3<i32>(1, 0, 0), layer, seed), u.x);
+    let x10 = mix(lattice(cell + vec3<i32>(0, 1, 0), layer, seed), lattice(cell + vec3<i32>(1, 1, 0), layer, seed), u.x);
+    let x01 = mix(lattice(cell + vec3<i32>(0, 0, 1), layer, seed), lattice(cell + vec3<i32>(1, 0, 1), layer, seed), u.x);
+    let x11 = mix(lattice(cell + vec3<i32>(0, 1, 1), layer, seed), lattice(cell + vec3<i32>(1, 1, 1), layer, seed), u.x);
+    return mix(mix(x00, x10, u.y), mix(x01, x11, u.y), u.z);
+}
+
+// Octaves of value noise at doubling frequency and halving amplitude, in 0..1.
+fn fbm(p: vec3<f32>, octaves: u32, layer: u32, seed: u32) -> f32 {
+    var sum = 0.0;
+    var amplitude = 1.0;
+    var total = 0.0;
+    var frequency = 1.0;
+    for (var octave = 0u; octave < max(octaves, 1u); octave = octave + 1u) {
+        sum = sum + amplitude * value_noise(p * frequency, layer, seed + octave * 0x9e3779b9u);
+        total = total + amplitude;
+        amplitude = amplitude * 0.5;
+        frequency = frequency * 2.0;
+    }
+    return sum / total;
+}
+
+@fragment
+fn fs_nebula(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let dir = face_direction(face.index, position.xy / face.size);
+    var color = nebula.background.rgb;
+    for (var i = 0u; i < nebula.count; i = i + 1u) {
+        let layer = nebula.layers[i];
+        let density = fbm(dir * layer.frequency, layer.octaves, i, layer.seed);
+        let cloud = smoothstep(layer.coverage, 1.0, density);
+        color = color + layer.color.rgb * layer.color.a * cloud;
+    }
+    return vec4<f32>(color, 1.0);
+}
+
+struct StarInput {
+    @location(0) direction: vec3<f32>,
+    // Standard deviation of the star's glow, in face coordinates
+    @location(1) radius: f32,
+    @location(2) color: vec3<f32>,
+};
+
+struct StarOutput {
+    @builtin(position) position: vec4<f32>,
+    // Offset from the star's center, in standard deviations
+    @location(0) offset: vec2<f32>,
+    @location(1) color: vec3<f32>,
+};
+
+@vertex
+fn vs_star(@builtin(vertex_index) vertex_index: u32, star: StarInput) -> StarOutput {
+    let corner = vec2<f32>(f32(vertex_index & 1u), f32(vertex_index >> 1u)) * 2.0 - 1.0;
+    // Stars smaller than a texel are spread over one, dimmed to keep their
+    // light, so the sky looks the same at any resolution.
+    let radius = max(star.radius, 1.2 / face.size);
+    let clip = face_clip(face.index, star.direction);
+
+    var out: StarOutput;
+    out.position = vec4<f32>(clip.xy + corner * 3.0 * radius * clip.w, 0.0, clip.w);
+    out.offset = corner * 3.0;
+    out.color = star.color * (star.radius * star.radius) / (radius * radius);
+    return out;
+}
+
+@fragment
+fn fs_star(in: StarOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.color * exp(-0.5 * dot(in.offset, in.offset)), 0.0);
+}
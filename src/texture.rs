@@ -1,19 +1,184 @@
 use image::GenericImageView;
+use serde::{Deserialize, Serialize};
 
-#[repr(C)]
-#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-pub struct Raw {
-    vec4: [f32; 4],
+use crate::mipmap::{self, MipmapGenerator};
+
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Adapt a depth comparison to the projection. Reverse-Z projections put the
+/// near plane at depth 1 and the far plane at 0, so comparisons flip.
+pub fn depth_compare(compare: wgpu::CompareFunction, reverse_z: bool) -> wgpu::CompareFunction {
+    use wgpu::CompareFunction::*;
+    match (compare, reverse_z) {
+        (Less, true) => Greater,
+        (LessEqual, true) => GreaterEqual,
+        (Greater, true) => Less,
+        (GreaterEqual, true) => LessEqual,
+        (compare, _) => compare,
+    }
+}
+
+/// Depth of the far plane, which the depth buffer is cleared to.
+pub fn far_depth(reverse_z: bool) -> f32 {
+    if reverse_z {
+        0.0
+    } else {
+        1.0
+    }
+}
+
+/// Create a depth attachment matching the size of the render target.
+pub fn create_depth_view(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Depth"),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+/// What happens to texture coordinates outside `0..1`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Wrap {
+    #[default]
+    Clamp,
+    Repeat,
+    Mirror,
+}
+
+impl From<Wrap> for wgpu::AddressMode {
+    fn from(wrap: Wrap) -> Self {
+        match wrap {
+            Wrap::Clamp => wgpu::AddressMode::ClampToEdge,
+            Wrap::Repeat => wgpu::AddressMode::Repeat,
+            Wrap::Mirror => wgpu::AddressMode::MirrorRepeat,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Filter {
+    Nearest,
+    #[default]
+    Linear,
+}
+
+impl From<Filter> for wgpu::FilterMode {
+    fn from(filter: Filter) -> Self {
+        match filter {
+            Filter::Nearest => wgpu::FilterMode::Nearest,
+            Filter::Linear => wgpu::FilterMode::Linear,
+        }
+    }
+}
+
+/// How a texture is stored and sampled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextureOptions {
+    pub wrap_u: Wrap,
+    pub wrap_v: Wrap,
+    pub mag_filter: Filter,
+    pub min_filter: Filter,
+    /// Blending between mip levels.
+    pub mipmap_filter: Filter,
+    /// Most samples taken along a slanted view, from 1 to 16. Above 1, all
+    /// filters must be `Linear`.
+    pub anisotropy: u16,
+    /// Colors encoded as sRGB, decoded when sampled. Off for data such as
+    /// normal maps.
+    pub srgb: bool,
+    /// Multiply colors by alpha when loading. Shape materials with such a
+    /// base texture blend as premultiplied, with opacity fading color too.
+    pub premultiply_alpha: bool,
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        TextureOptions {
+            wrap_u: Wrap::Clamp,
+            wrap_v: Wrap::Clamp,
+            mag_filter: Filter::Linear,
+            min_filter: Filter::Nearest,
+            mipmap_filter: Filter::Nearest,
+            anisotropy: 1,
+            srgb: true,
+            premultiply_alpha: false,
+        }
+    }
+}
+
+impl TextureOptions {
+    /// Linear data, as normal and metallic-roughness maps need.
+    pub fn linear() -> Self {
+        TextureOptions {
+            srgb: false,
+            ..Default::default()
+        }
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if !(1..=16).contains(&self.anisotropy) {
+            anyhow::bail!("Anisotropy is {}, not from 1 to 16", self.anisotropy);
+        }
+        let filters = [self.mag_filter, self.min_filter, self.mipmap_filter];
+        if self.anisotropy > 1 && filters.contains(&Filter::Nearest) {
+            anyhow::bail!("Anisotropic filtering needs all filters Linear");
+        }
+        Ok(())
+    }
+}
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Multiply color by alpha, in linear space for sRGB data.
+fn premultiply_alpha(rgba: &mut image::RgbaImage, srgb: bool) {
+    for pixel in rgba.pixels_mut() {
+        let alpha = pixel[3] as f32 / 255.0;
+        for c in &mut pixel.0[..3] {
+            let value = *c as f32 / 255.0;
+            let value = if srgb {
+                linear_to_srgb(srgb_to_linear(value) * alpha)
+            } else {
+                value * alpha
+            };
+            *c = (value * 255.0).round() as u8;
+        }
+    }
 }
 
 pub struct Texture {
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
     pub sampler: wgpu::Sampler,
-    pub bind_group_layout: wgpu::BindGroupLayout,
-    pub bind_group: wgpu::BindGroup,
-    pub raw: Raw,
-    pub buffer: wgpu::Buffer,
+    /// Colors were multiplied by alpha when loading.
+    pub premultiplied_alpha: bool,
 }
 
 impl Texture {
@@ -21,22 +186,25 @@ impl Texture {
     pub fn from_bytes(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
         bytes: &[u8],
         label: &str,
-        is_normal_map: bool,
+        options: &TextureOptions,
     ) -> anyhow::Result<Self> {
         let img = image::load_from_memory(bytes)?;
-        Self::from_image(device, queue, &img, Some(label), is_normal_map)
+        Self::from_image(device, queue, mipmaps, &img, Some(label), options)
     }
 
     /// Create a `Texture` from a `image::DynamicImage`.
     pub fn from_image(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
         img: &image::DynamicImage,
         label: Option<&str>,
-        is_normal_map: bool,
+        options: &TextureOptions,
     ) -> anyhow::Result<Self> {
+        options.validate()?;
 
         /*
         //img: &image::DynamicImage,
@@ -48,7 +216,10 @@ impl Texture {
         let img = image::load_from_memory(&data)?;
         */
 
-        let rgba = img.to_rgba8();
+        let mut rgba = img.to_rgba8();
+        if options.premultiply_alpha {
+            premultiply_alpha(&mut rgba, options.srgb);
+        }
         let dimensions = img.dimensions();
         let size = wgpu::Extent3d {
             width: dimensions.0,
@@ -59,15 +230,17 @@ impl Texture {
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label,
             size,
-            mip_level_count: 1,
+            mip_level_count: mipmap::level_count(dimensions.0, dimensions.1),
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: if is_normal_map {
-                wgpu::TextureFormat::Rgba8Unorm
-            } else {
+            format: if options.srgb {
                 wgpu::TextureFormat::Rgba8UnormSrgb
+            } else {
+                wgpu::TextureFormat::Rgba8Unorm
             },
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::RENDER_ATTACHMENT,
             view_formats: &[],
         });
 
@@ -86,94 +259,26 @@ impl Texture {
             },
             size,
         );
+        mipmaps.generate(device, queue, &texture);
 
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
 
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_u: options.wrap_u.into(),
+            address_mode_v: options.wrap_v.into(),
             address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
+            mag_filter: options.mag_filter.into(),
+            min_filter: options.min_filter.into(),
+            mipmap_filter: options.mipmap_filter.into(),
+            anisotropy_clamp: options.anisotropy,
             ..Default::default()
         });
 
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[
-                // View
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        multisampled: false,
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                    },
-                    count: None,
-                },
-                // Sampler
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 2,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-            ],
-            label,
-        });
-
-        let raw = Raw { vec4: [0.0, 0.0, 0.0, 1.0] };
-
-        use wgpu::util::DeviceExt;
-        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice(&[raw]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: buffer.as_entire_binding(),
-                }
-            ],
-            label,
-        });
-
         Ok(Self {
             texture,
             view,
             sampler,
-            bind_group_layout,
-            bind_group,
-            raw,
-            buffer,
+            premultiplied_alpha: options.premultiply_alpha,
         })
     }
-
-    pub fn update(&mut self, queue: &wgpu::Queue, alpha: f32) {
-        self.raw = Raw { vec4: [0.0, 0.0, 0.0, alpha] };
-        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.raw]));
-    }
 }
@@ -1,18 +1,89 @@
-use std::collections::VecDeque;
+#[cfg(target_arch = "wasm32")]
+use std::cell::RefCell;
+use std::{
+    collections::{HashMap, VecDeque},
+    rc::Rc,
+};
 
-use winit::{dpi::PhysicalSize, window::Window};
+use winit::{
+    dpi::PhysicalSize,
+    event::{ModifiersState, VirtualKeyCode},
+    window::Window,
+};
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::capture::FrameCapture;
 use crate::{
-    camera::{Camera, CameraController, Projection},
+    animation::{Animation, Curve, CurveKey, NoiseAnimation, Params, Tween, UniformFields},
+    bindings::{Action, Binding, Bindings, Input},
+    camera::{Camera, CameraController, CameraMode, Move, Projection, ProjectionKind},
+    camera_path::{CameraPath, EaseMode, Easing, PathPlayer, Pose, Repeat},
+    capture,
+    clock::SimClock,
     draw_shape::{DrawShape, DrawShapePipeline},
-    resources::load_texture,
-    rotation::RotationY,
-    skybox::Skybox,
-    texture::Texture,
+    instance::Instance,
+    material::{MaterialParams, ShapeMaterial},
+    mesh::Vertex,
+    mipmap::MipmapGenerator,
+    model::{self, Model, ModelVertex},
+    noise::Noise,
+    object::{ObjectUniforms, Transform},
+    render_pass::TrackedRenderPass,
+    resources::{load_gltf, load_model, load_texture, solid_texture, WHITE},
+    scene::Scene,
+    skybox::{FetchedSkybox, Skybox, SkyboxSource},
+    starfield::Starfield,
+    texture::{self, Texture, TextureOptions},
 };
 
+const JITTER_PARAMS: [&str; 4] = ["jitter_r", "jitter_g", "jitter_b", "jitter_a"];
+
+/// Parameters the uniforms are bound to: the spin angle of the shapes, the
+/// jitter of `vs_background` and the opacity of the default material.
+fn default_params() -> Params {
+    let mut params = Params::default();
+    params.insert(
+        "spin",
+        Animation::Noise(NoiseAnimation {
+            noise: Noise::new(47, 2.0, 3),
+            integrate: true,
+            ..Default::default()
+        }),
+    );
+    for (seed, name) in (47..).zip(JITTER_PARAMS) {
+        params.insert(
+            name,
+            Animation::Noise(NoiseAnimation {
+                noise: Noise::new(seed, 1.0, 1),
+                ..Default::default()
+            }),
+        );
+    }
+    params.insert("alpha", Animation::Constant(1.0));
+    params
+}
+
+/// Where the GUI saves and loads camera paths.
+#[cfg(not(target_arch = "wasm32"))]
+const CAMERA_PATH_FILE: &str = "camera_path.ron";
+
+/// Where the GUI saves key bindings. Preferred over `res/bindings.ron` at startup.
+#[cfg(not(target_arch = "wasm32"))]
+pub const BINDINGS_FILE: &str = "bindings.ron";
+
 pub struct GuiState {
-    slider: f32,
+    /// Settings for keyframes added to the camera path.
+    path_duration: f32,
+    path_easing: Easing,
+    /// Action waiting for the next key or button press to bind.
+    rebinding: Option<Action>,
+    /// Skybox picked in the GUI, loaded in the next `update`.
+    skybox_choice: Option<SkyboxSource>,
+    /// Starfield seed being dragged, applied when the drag ends.
+    starfield_seed: Option<u32>,
+    /// Cross, strip or panorama image typed into the GUI.
+    skybox_path: String,
+    visible: bool,
 }
 
 #[derive(Default)]
@@ -24,29 +95,205 @@ pub struct RenderView {
     size: winit::dpi::PhysicalSize<u32>,
     #[allow(unused)]
     scale_factor: f32,
-    surface: wgpu::Surface,
+    target: RenderTarget,
+    depth: wgpu::TextureView,
     device: wgpu::Device,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
     camera: Camera,
     shader: wgpu::ShaderModule,
     pipeline_layout: wgpu::PipelineLayout,
-    rotation: RotationY,
+    /// Matches `Rotation` in shader.wgsl and model.wgsl.
+    rotation: wgpu::Buffer,
+    rotation_fields: UniformFields,
+    /// Drives the opacity of the default material.
+    material_fields: UniformFields,
+    pub params: Params,
+    objects: ObjectUniforms,
     pub camera_controller: CameraController,
     projection: Projection,
     pub mouse_pressed: bool,
     draw_shapes: VecDeque<DrawShapePipeline>,
     pub keys: Keys,
+    pub bindings: Bindings,
+    modifiers: ModifiersState,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub capture: FrameCapture,
+    pub clock: SimClock,
+    pub camera_path: PathPlayer,
     skybox: Skybox,
+    pub skybox_source: SkyboxSource,
+    /// Skybox fetched in the background on the web, switched to in the next
+    /// `update`.
+    #[cfg(target_arch = "wasm32")]
+    fetched_skybox: Rc<RefCell<Option<anyhow::Result<FetchedSkybox>>>>,
     skybox_pipeline: wgpu::RenderPipeline,
+    mipmaps: MipmapGenerator,
+    material_layout: wgpu::BindGroupLayout,
+    model_pipeline: wgpu::RenderPipeline,
+    models: Vec<Model>,
     pub egui_context: egui::Context,
     egui_renderer: egui_wgpu::Renderer,
     pub egui_repaint: bool,
-    texture: Texture,
+    /// Drawn with `fs_texture` by shapes without a material of their own.
+    material: ShapeMaterial,
+    shape_material_layout: wgpu::BindGroupLayout,
+    /// Loaded textures, shared by the materials using them. `load_scene`
+    /// drops those no shape uses anymore.
+    textures: HashMap<(String, TextureOptions), Rc<Texture>>,
+    /// Emissive texture of materials without one.
+    white_texture: Rc<Texture>,
     gui: GuiState,
     //noise: Vec<f32>,
 }
 
+/// Where a `RenderView` draws its frames.
+enum RenderTarget {
+    /// Swapchain of a window surface.
+    Surface(wgpu::Surface),
+    /// Offscreen color texture for rendering without a display.
+    Offscreen(wgpu::Texture),
+}
+
+impl RenderTarget {
+    fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        match self {
+            RenderTarget::Surface(surface) => surface.configure(device, config),
+            RenderTarget::Offscreen(texture) => *texture = create_offscreen(device, config),
+        }
+    }
+}
+
+fn create_offscreen(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Offscreen"),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: config.usage,
+        view_formats: &[],
+    })
+}
+
+async fn request_device(
+    adapter: &wgpu::Adapter,
+) -> Result<(wgpu::Device, wgpu::Queue), wgpu::RequestDeviceError> {
+    adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                // Push constants aren't available on fallback or GL adapters.
+                features: adapter.features() & wgpu::Features::PUSH_CONSTANTS,
+
+                #[cfg(target_arch = "wasm32")]
+                limits: wgpu::Limits::downlevel_webgl2_defaults(),
+
+                #[cfg(not(target_arch = "wasm32"))]
+                limits: wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits()),
+            },
+            None,
+        )
+        .await
+}
+
+fn create_sky_pipeline(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    shader: &wgpu::ShaderModule,
+    pipeline_layout: &wgpu::PipelineLayout,
+    reverse_z: bool,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Sky"),
+        layout: Some(pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_sky",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_sky",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: config.format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            //targets: &[Some(config.format.into())],
+        }),
+        primitive: wgpu::PrimitiveState {
+            front_face: wgpu::FrontFace::Cw,
+            ..Default::default()
+        },
+        // The sky sits on the far plane, behind everything else.
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: texture::DEPTH_FORMAT,
+            depth_write_enabled: false,
+            depth_compare: texture::depth_compare(wgpu::CompareFunction::LessEqual, reverse_z),
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+fn create_model_pipeline(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    reverse_z: bool,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(wgpu::include_wgsl!("model.wgsl"));
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Model"),
+        bind_group_layouts,
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Model"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_model",
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<ModelVertex>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: ModelVertex::ATTRIBUTES,
+            }],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_model",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: config.format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            cull_mode: Some(wgpu::Face::Back),
+            ..Default::default()
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: texture::DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: texture::depth_compare(wgpu::CompareFunction::Less, reverse_z),
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
 impl RenderView {
     pub async fn new(window: &Window) -> Self {
         let size = window.inner_size();
@@ -68,23 +315,7 @@ impl RenderView {
             })
             .await
             .unwrap();
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: None,
-                    //features: wgpu::Features::empty(),
-                    features: wgpu::Features::PUSH_CONSTANTS,
-
-                    #[cfg(target_arch = "wasm32")]
-                    limits: wgpu::Limits::downlevel_webgl2_defaults(),
-
-                    #[cfg(not(target_arch = "wasm32"))]
-                    limits: wgpu::Limits::default(),
-                },
-                None,
-            )
-            .await
-            .unwrap();
+        let (device, queue) = request_device(&adapter).await.unwrap();
         let capabilities = surface.get_capabilities(&adapter);
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
@@ -96,8 +327,80 @@ impl RenderView {
             view_formats: vec![capabilities.formats[0]],
         };
         surface.configure(&device, &config);
+
+        Self::with_target(
+            device,
+            queue,
+            config,
+            RenderTarget::Surface(surface),
+            scale_factor,
+        )
+        .await
+    }
+
+    /// Create a view that renders into an offscreen texture instead of a window.
+    /// Falls back to a software adapter when no hardware adapter is available,
+    /// and fails when there is neither.
+    pub async fn new_headless(width: u32, height: u32) -> anyhow::Result<Self> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..wgpu::InstanceDescriptor::default()
+        });
+
+        let mut options = wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter: false,
+            compatible_surface: None,
+        };
+        let adapter = match instance.request_adapter(&options).await {
+            Some(adapter) => adapter,
+            None => {
+                options.force_fallback_adapter = true;
+                instance
+                    .request_adapter(&options)
+                    .await
+                    .ok_or_else(|| anyhow::anyhow!("No adapter available for headless rendering"))?
+            }
+        };
+        log::info!("Headless adapter: {:?}", adapter.get_info());
+
+        let (device, queue) = request_device(&adapter)
+            .await
+            .map_err(|e| anyhow::anyhow!("No device for headless rendering: {e}"))?;
+        let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            view_formats: vec![format],
+        };
+        let target = RenderTarget::Offscreen(create_offscreen(&device, &config));
+
+        Ok(Self::with_target(device, queue, config, target, 1.0).await)
+    }
+
+    async fn with_target(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        config: wgpu::SurfaceConfiguration,
+        target: RenderTarget,
+        scale_factor: f32,
+    ) -> Self {
+        let size = PhysicalSize::new(config.width, config.height);
         let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
-        let rotation = RotationY::new(&device);
+        let params = default_params();
+        let mut rotation_fields = UniformFields::new(vec![0.0; 8])
+            .bind("jitter_r", 0)
+            .bind("jitter_g", 1)
+            .bind("jitter_b", 2)
+            .bind("jitter_a", 3)
+            .bind("spin", 4);
+        let rotation = rotation_fields.create_buffer(&device, "Rotation");
+        rotation_fields.write(&queue, &rotation, &params, 0.0);
+        let objects = ObjectUniforms::new(&device, &rotation);
 
         let keys = Keys::default();
 
@@ -112,47 +415,67 @@ impl RenderView {
         );
         camera.update_view_proj(&projection);
 
-        let skybox = Skybox::new(&device, &queue);
+        let mipmaps = MipmapGenerator::new(&device);
+        let skybox_source = SkyboxSource::default();
+        let skybox = match Skybox::load(&device, &queue, &mipmaps, &skybox_source).await {
+            Ok(skybox) => skybox,
+            Err(e) => {
+                log::error!("Failed to load skybox: {}", e);
+                Skybox::solid(&device, &queue, &mipmaps, [0, 0, 0, 255])
+            }
+        };
 
-        let texture = load_texture("baba.png", &device, &queue).await.unwrap();
+        let texture = load_texture(
+            "baba.png",
+            &TextureOptions::default(),
+            &device,
+            &queue,
+            &mipmaps,
+        )
+        .await
+        .unwrap();
+        let white_texture = Rc::new(
+            solid_texture(WHITE, &TextureOptions::default(), &device, &queue, &mipmaps).unwrap(),
+        );
+        let shape_material_layout = ShapeMaterial::bind_group_layout(&device);
+        let material = ShapeMaterial::new(
+            &device,
+            &shape_material_layout,
+            Rc::new(texture),
+            white_texture.clone(),
+            MaterialParams::default(),
+        );
+        let material_fields = UniformFields::new(material.uniform_values())
+            .bind("alpha", MaterialParams::OPACITY_INDEX);
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: None,
             bind_group_layouts: &[
-                &rotation.bind_group_layout,
+                &objects.bind_group_layout,
                 &camera.bind_group_layout,
                 &skybox.bind_group_layout,
-                &texture.bind_group_layout,
+                &shape_material_layout,
             ],
             push_constant_ranges: &[],
         });
 
-        let skybox_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Sky"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_sky",
-                buffers: &[],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_sky",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                //targets: &[Some(config.format.into())],
-            }),
-            primitive: wgpu::PrimitiveState {
-                front_face: wgpu::FrontFace::Cw,
-                ..Default::default()
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-        });
+        let skybox_pipeline =
+            create_sky_pipeline(&device, &config, &shader, &pipeline_layout, false);
+
+        let material_layout = model::Material::bind_group_layout(&device);
+        let model_pipeline = create_model_pipeline(
+            &device,
+            &config,
+            &[
+                &objects.bind_group_layout,
+                &camera.bind_group_layout,
+                &skybox.bind_group_layout,
+                &material_layout,
+            ],
+            false,
+        );
+
+        let depth = texture::create_depth_view(&device, &config);
 
         let egui_context = egui::Context::default();
         let egui_renderer = egui_wgpu::Renderer::new(&device, config.format, None, 1);
@@ -161,11 +484,18 @@ impl RenderView {
 
         RenderView {
             size,
-            surface,
+            target,
+            depth,
             device,
             queue,
             config,
             keys,
+            bindings: Bindings::default(),
+            modifiers: ModifiersState::empty(),
+            #[cfg(not(target_arch = "wasm32"))]
+            capture: FrameCapture::new("captures"),
+            clock: SimClock::default(),
+            camera_path: PathPlayer::default(),
             camera,
             camera_controller,
             projection,
@@ -174,34 +504,263 @@ impl RenderView {
             shader,
             pipeline_layout,
             rotation,
+            rotation_fields,
+            material_fields,
+            params,
+            objects,
             skybox,
+            skybox_source,
+            #[cfg(target_arch = "wasm32")]
+            fetched_skybox: Rc::default(),
             skybox_pipeline,
+            mipmaps,
+            material_layout,
+            model_pipeline,
+            models: Vec::new(),
             egui_context,
             egui_renderer,
             egui_repaint: false,
-            texture,
+            material,
+            shape_material_layout,
+            textures: HashMap::new(),
+            white_texture,
             scale_factor,
-            gui: GuiState { slider: 1.0 },
+            gui: GuiState {
+                path_duration: 2.0,
+                path_easing: Easing::Sine,
+                rebinding: None,
+                skybox_choice: None,
+                starfield_seed: None,
+                skybox_path: String::new(),
+                visible: true,
+            },
             //noise,
         }
     }
 
     pub fn push_shape(&mut self, shape: DrawShape) {
+        self.push_shape_with_material(shape, None);
+    }
+
+    /// Push a shape drawn with its own material instead of the default one.
+    pub fn push_shape_with_material(&mut self, shape: DrawShape, material: Option<ShapeMaterial>) {
         self.draw_shapes.push_back(DrawShapePipeline::new(
             &self.device,
             &self.config,
             shape,
+            material,
             &self.shader,
             &self.pipeline_layout,
+            self.projection.is_reverse_z(),
         ));
     }
 
+    /// Load a texture from `res/`, or reuse it if already loaded with the
+    /// same options.
+    pub async fn load_texture(
+        &mut self,
+        file_name: &str,
+        options: &TextureOptions,
+    ) -> anyhow::Result<Rc<Texture>> {
+        let key = (file_name.to_string(), *options);
+        if let Some(texture) = self.textures.get(&key) {
+            return Ok(texture.clone());
+        }
+        let texture = Rc::new(
+            load_texture(file_name, options, &self.device, &self.queue, &self.mipmaps).await?,
+        );
+        self.textures.insert(key, texture.clone());
+        Ok(texture)
+    }
+
+    /// A material for `fs_texture`. Without a base texture it shows the
+    /// default one, and without an emissive texture `emissive` is uniform.
+    pub fn create_material(
+        &self,
+        base_texture: Option<Rc<Texture>>,
+        emissive_texture: Option<Rc<Texture>>,
+        params: MaterialParams,
+    ) -> ShapeMaterial {
+        ShapeMaterial::new(
+            &self.device,
+            &self.shape_material_layout,
+            base_texture.unwrap_or_else(|| self.material.base_texture.clone()),
+            emissive_texture.unwrap_or_else(|| self.white_texture.clone()),
+            params,
+        )
+    }
+
+    /// Load an OBJ or glTF model from `res/`, picked by file extension.
+    pub async fn load_model(&mut self, file_name: &str) -> anyhow::Result<()> {
+        let model = self.read_model(file_name).await?;
+        self.models.push(model);
+        Ok(())
+    }
+
+    async fn read_model(&self, file_name: &str) -> anyhow::Result<Model> {
+        let extension = std::path::Path::new(file_name)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("gltf" | "glb") => {
+                load_gltf(
+                    file_name,
+                    &self.device,
+                    &self.queue,
+                    &self.mipmaps,
+                    &self.material_layout,
+                )
+                .await
+            }
+            _ => {
+                load_model(
+                    file_name,
+                    &self.device,
+                    &self.queue,
+                    &self.mipmaps,
+                    &self.material_layout,
+                )
+                .await
+            }
+        }
+    }
+
+    /// Replace the skybox, keeping the current one if loading fails.
+    pub async fn load_skybox(&mut self, source: &SkyboxSource) -> anyhow::Result<()> {
+        self.skybox = Skybox::load(&self.device, &self.queue, &self.mipmaps, source).await?;
+        self.skybox_source = source.clone();
+        Ok(())
+    }
+
+    /// Load the skybox picked in the GUI, and switch to it once its files are
+    /// read: right away on native, and after a background fetch on the web.
+    fn update_skybox(&mut self) {
+        if let Some(source) = self.gui.skybox_choice.take() {
+            #[cfg(target_arch = "wasm32")]
+            {
+                let fetched = self.fetched_skybox.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    *fetched.borrow_mut() = Some(source.fetch().await);
+                });
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            self.set_fetched_skybox(pollster::block_on(source.fetch()));
+        }
+        #[cfg(target_arch = "wasm32")]
+        if let Some(fetched) = self.fetched_skybox.take() {
+            self.set_fetched_skybox(fetched);
+        }
+    }
+
+    fn set_fetched_skybox(&mut self, fetched: anyhow::Result<FetchedSkybox>) {
+        let result = fetched.and_then(|fetched| {
+            self.skybox = Skybox::from_fetched(&self.device, &self.queue, &self.mipmaps, &fetched)?;
+            self.skybox_source = fetched.source;
+            Ok(())
+        });
+        if let Err(e) = result {
+            log::error!("Failed to load skybox: {}", e);
+        }
+    }
+
+    /// Replace the shapes, models, camera and skybox with those of `scene`.
+    pub async fn load_scene(&mut self, scene: &Scene) -> anyhow::Result<()> {
+        // Load everything before touching the view, so a failed load leaves
+        // the previous scene as it was.
+        let skybox = match &scene.skybox {
+            Some(source) => Some((
+                Skybox::load(&self.device, &self.queue, &self.mipmaps, source).await?,
+                source.clone(),
+            )),
+            None => None,
+        };
+
+        let mut shapes = Vec::new();
+        for scene_shape in &scene.shapes {
+            let material = if scene_shape.has_material() {
+                let options = &scene_shape.texture_options;
+                let base_texture = match &scene_shape.texture {
+                    Some(file_name) => Some(self.load_texture(file_name, options).await?),
+                    None => None,
+                };
+                let emissive_texture = match &scene_shape.emissive_texture {
+                    Some(file_name) => Some(self.load_texture(file_name, options).await?),
+                    None => None,
+                };
+                let params = scene_shape.material.unwrap_or_default();
+                Some(self.create_material(base_texture, emissive_texture, params))
+            } else {
+                None
+            };
+            shapes.push((scene_shape.to_draw_shape(), material));
+        }
+
+        let mut models = Vec::new();
+        for file_name in &scene.models {
+            models.push(self.read_model(file_name).await?);
+        }
+
+        let camera_path = match &scene.camera_path {
+            Some(file_name) => Some(CameraPath::load(file_name).await?),
+            None => None,
+        };
+
+        if let Some((skybox, source)) = skybox {
+            self.skybox = skybox;
+            self.skybox_source = source;
+        }
+
+        self.draw_shapes.clear();
+        for (shape, material) in shapes {
+            self.push_shape_with_material(shape, material);
+        }
+        // Forget textures that only the replaced shapes used.
+        self.textures
+            .retain(|_, texture| Rc::strong_count(texture) > 1);
+
+        self.models = models;
+
+        for (name, animation) in &scene.params {
+            self.params.insert(name, animation.clone());
+        }
+        self.write_params(0.0);
+
+        self.camera_path = PathPlayer::default();
+        if let Some(path) = camera_path {
+            self.set_camera_path(path);
+        }
+
+        // Without a scene camera, look through the first camera of a model.
+        let model_camera = self.models.iter().flat_map(|m| &m.cameras).next().copied();
+        match (&scene.camera, model_camera) {
+            (None, Some(camera)) => {
+                if let Some(fovy) = camera.fovy {
+                    self.projection.set_fovy(fovy);
+                }
+                self.set_camera(camera.position, camera.yaw, camera.pitch);
+            }
+            (camera, _) => {
+                let camera = camera.clone().unwrap_or_default();
+                let [x, y, z] = camera.position;
+                self.set_camera(
+                    (x, y, z),
+                    cgmath::Deg(camera.yaw),
+                    cgmath::Deg(camera.pitch),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.size = new_size;
             self.config.width = new_size.width;
             self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
+            self.target.resize(&self.device, &self.config);
+            self.depth = texture::create_depth_view(&self.device, &self.config);
         }
         self.projection.resize(new_size.width, new_size.height);
     }
@@ -210,112 +769,376 @@ impl RenderView {
         self.resize(self.size);
     }
 
-    pub fn update(&mut self, dt: instant::Duration) {
-        let step = dt.as_secs_f32();
+    /// Place the camera, e.g. to render a fixed view.
+    pub fn set_camera<
+        V: Into<cgmath::Point3<f32>>,
+        Y: Into<cgmath::Rad<f32>>,
+        P: Into<cgmath::Rad<f32>>,
+    >(
+        &mut self,
+        position: V,
+        yaw: Y,
+        pitch: P,
+    ) {
+        self.camera.set_pose(position, yaw, pitch);
+        self.write_camera();
+    }
+
+    pub fn camera_pose(&self) -> Pose {
+        Pose {
+            position: self.camera.position,
+            yaw: self.camera.yaw().into(),
+            pitch: self.camera.pitch().into(),
+        }
+    }
+
+    /// Replace the camera path and play it.
+    pub fn set_camera_path(&mut self, path: CameraPath) {
+        self.camera_path.path = path;
+        self.play_camera_path();
+    }
+
+    /// Append the current camera pose to the camera path.
+    pub fn add_camera_keyframe(&mut self) {
+        let pose = self.camera_pose();
+        self.camera_path
+            .path
+            .push(pose, self.gui.path_duration, self.gui.path_easing);
+    }
+
+    pub fn play_camera_path(&mut self) {
+        // Orbiting would pull the camera back toward its target.
+        self.set_camera_mode(CameraMode::Fly);
+        self.camera_path.play();
+    }
+
+    pub fn toggle_camera_path(&mut self) {
+        if self.camera_path.is_playing() {
+            self.camera_path.stop();
+        } else {
+            self.play_camera_path();
+        }
+    }
+
+    pub fn projection_kind(&self) -> ProjectionKind {
+        self.projection.kind()
+    }
+
+    /// Switch projections, rebuilding the pipelines when the depth direction changes.
+    pub fn set_projection(&mut self, kind: ProjectionKind) {
+        let was_reverse_z = self.projection.is_reverse_z();
+        self.projection.set_kind(kind);
+        let reverse_z = self.projection.is_reverse_z();
+        if reverse_z != was_reverse_z {
+            self.skybox_pipeline = create_sky_pipeline(
+                &self.device,
+                &self.config,
+                &self.shader,
+                &self.pipeline_layout,
+                reverse_z,
+            );
+            self.model_pipeline = create_model_pipeline(
+                &self.device,
+                &self.config,
+                &[
+                    &self.objects.bind_group_layout,
+                    &self.camera.bind_group_layout,
+                    &self.skybox.bind_group_layout,
+                    &self.material_layout,
+                ],
+                reverse_z,
+            );
+            for shape in self.draw_shapes.iter_mut() {
+                shape.rebuild(
+                    &self.device,
+                    &self.config,
+                    &self.shader,
+                    &self.pipeline_layout,
+                    reverse_z,
+                );
+            }
+        }
+        self.write_camera();
+    }
+
+    /// Visible height of the orthographic projection, in world units.
+    pub fn set_ortho_height(&mut self, height: f32) {
+        self.projection.set_ortho_height(height);
+        self.write_camera();
+    }
+
+    pub fn set_camera_mode(&mut self, mode: CameraMode) {
+        self.camera_controller.set_mode(mode, &self.camera);
+    }
+
+    pub fn toggle_camera_mode(&mut self) {
+        self.camera_controller.toggle_mode(&self.camera);
+    }
+
+    pub fn set_modifiers(&mut self, modifiers: ModifiersState) {
+        self.modifiers = modifiers;
+    }
+
+    /// Handle a key or mouse button, returning whether it was used. While
+    /// the GUI waits for a new binding, the press is bound instead.
+    pub fn process_input(&mut self, input: Input, pressed: bool) -> bool {
+        if let Some(action) = self.gui.rebinding {
+            if pressed {
+                self.gui.rebinding = None;
+                if input != Input::Key(VirtualKeyCode::Escape) {
+                    self.bindings.set(action, self.binding_for(input));
+                }
+            }
+            return true;
+        }
+
+        if pressed {
+            match self.bindings.action(input, self.modifiers) {
+                Some(action) => self.apply_action(action, true),
+                None => return false,
+            }
+        } else {
+            let actions: Vec<Action> = self.bindings.held_actions(input).collect();
+            if actions.is_empty() {
+                return false;
+            }
+            for action in actions {
+                self.apply_action(action, false);
+            }
+        }
+        true
+    }
+
+    /// A binding for `input` with the modifiers currently held. Modifier keys
+    /// are bound on their own, as they count as held while pressed.
+    fn binding_for(&self, input: Input) -> Binding {
+        use VirtualKeyCode::*;
+
+        let mut binding = Binding::new(input);
+        if !matches!(
+            input,
+            Input::Key(LShift | RShift | LControl | RControl | LAlt | RAlt | LWin | RWin)
+        ) {
+            binding.modifiers = self.modifiers.into();
+        }
+        binding
+    }
+
+    /// Start or stop a held action, or trigger any other action on press.
+    pub fn apply_action(&mut self, action: Action, pressed: bool) {
+        let direction = match action {
+            Action::MoveForward => Some(Move::Forward),
+            Action::MoveBackward => Some(Move::Backward),
+            Action::MoveLeft => Some(Move::Left),
+            Action::MoveRight => Some(Move::Right),
+            Action::MoveUp => Some(Move::Up),
+            Action::MoveDown => Some(Move::Down),
+            _ => None,
+        };
+        if let Some(direction) = direction {
+            self.camera_controller.process_move(direction, pressed);
+            return;
+        }
+
+        match action {
+            Action::Look => self.mouse_pressed = pressed,
+            Action::Pan => self.camera_controller.set_pan(pressed),
+            _ if !pressed => {}
+            Action::ToggleRotation => self.keys.rotation = !self.keys.rotation,
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::CaptureFrame => self.capture.capture_frame(),
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::ToggleRecording => self.capture.toggle_sequence(),
+            Action::ToggleCameraMode => self.toggle_camera_mode(),
+            Action::AddKeyframe => self.add_camera_keyframe(),
+            Action::ToggleCameraPath => self.toggle_camera_path(),
+            Action::TogglePause => self.clock.toggle_pause(),
+            Action::SingleStep => self.clock.single_step(),
+            _ => {}
+        }
+    }
+
+    /// Pin the spin at `angle`.
+    pub fn set_rotation<A: Into<cgmath::Rad<f32>>>(&mut self, angle: A) {
+        self.set_param("spin", Animation::Constant(angle.into().0));
+    }
+
+    /// Animate a parameter, e.g. `spin`, `jitter_r` or `alpha`.
+    pub fn set_param(&mut self, name: &str, animation: Animation) {
+        self.params.insert(name, animation);
+        self.write_params(0.0);
+    }
+
+    /// Jump all parameters to `time` seconds along their animations.
+    pub fn seek_params(&mut self, time: f32) {
+        self.params.seek(time);
+        self.write_params(0.0);
+    }
+
+    fn write_params(&mut self, lead: f32) {
+        self.rotation_fields
+            .write(&self.queue, &self.rotation, &self.params, lead);
+        self.material_fields
+            .write(&self.queue, &self.material.buffer, &self.params, lead);
+    }
 
-        if self.keys.rotation {
-            self.rotation.increment_angle(&self.queue, step);
+    /// Place the `index`th shape in world space.
+    pub fn set_shape_transform(&mut self, index: usize, transform: Transform) {
+        if let Some(shape) = self.draw_shapes.get_mut(index) {
+            shape.shape.transform = transform;
         }
-        self.camera_controller.update_camera(&mut self.camera, dt);
+    }
+
+    /// Add an instance to the `shape`th shape, returning its index.
+    pub fn add_instance(&mut self, shape: usize, instance: Instance) -> Option<usize> {
+        let instances = self.draw_shapes.get_mut(shape)?.instances_mut();
+        instances.push(instance);
+        Some(instances.len() - 1)
+    }
+
+    pub fn update_instance(&mut self, shape: usize, index: usize, instance: Instance) {
+        if let Some(shape) = self.draw_shapes.get_mut(shape) {
+            if let Some(current) = shape.instances_mut().get_mut(index) {
+                *current = instance;
+            }
+        }
+    }
+
+    /// Remove an instance. The last instance of the shape takes its index.
+    pub fn remove_instance(&mut self, shape: usize, index: usize) -> Option<Instance> {
+        let instances = self.draw_shapes.get_mut(shape)?.instances_mut();
+        (index < instances.len()).then(|| instances.swap_remove(index))
+    }
+
+    /// Replace all instances of the `shape`th shape.
+    pub fn set_instances(&mut self, shape: usize, instances: Vec<Instance>) {
+        if let Some(shape) = self.draw_shapes.get_mut(shape) {
+            *shape.instances_mut() = instances;
+        }
+    }
+
+    /// Place the `index`th model in world space.
+    pub fn set_model_transform(&mut self, index: usize, transform: Transform) {
+        if let Some(model) = self.models.get_mut(index) {
+            model.transform = transform;
+        }
+    }
+
+    /// Pin the jitter offsets instead of sampling them from noise.
+    pub fn set_jitter(&mut self, jitter: [f32; 4]) {
+        for (name, value) in JITTER_PARAMS.iter().zip(jitter) {
+            self.params.insert(name, Animation::Constant(value));
+        }
+        self.write_params(0.0);
+    }
+
+    pub fn set_gui_visible(&mut self, visible: bool) {
+        self.gui.visible = visible;
+    }
+
+    fn write_camera(&mut self) {
         self.camera.update_view_proj(&self.projection);
         self.queue.write_buffer(
             &self.camera.buffer,
             0,
             bytemuck::cast_slice(&[self.camera.uniform]),
         );
+    }
 
-        use easer::functions::{Easing, Sine};
-        let slider = Sine::ease_in_out(self.gui.slider, 0.0, 1.0, 1.0);
-        self.texture.update(&self.queue, slider);
+    pub fn update(&mut self, dt: instant::Duration) {
+        // Parameters run on simulation time, with the spin held while
+        // rotation is off. The camera follows wall-clock time, so it stays
+        // free while paused.
+        self.update_skybox();
+        let steps = self.clock.advance(dt);
+        let step = self.clock.step().as_secs_f32();
+        self.params.set_held("spin", !self.keys.rotation);
+        self.params.advance(steps as f32 * step);
+        self.write_params(self.clock.alpha() * step);
+        match self.camera_path.advance(dt.as_secs_f32()) {
+            Some(pose) => self.camera.set_pose(pose.position, pose.yaw, pose.pitch),
+            None => self.camera_controller.update_camera(&mut self.camera, dt),
+        }
+        self.write_camera();
     }
 
     pub fn render(&mut self, egui_input: egui::RawInput) -> Result<(), wgpu::SurfaceError> {
-        let output = self.surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        let output = match &self.target {
+            RenderTarget::Surface(surface) => Some(surface.get_current_texture()?),
+            RenderTarget::Offscreen(_) => None,
+        };
 
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::default()),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            });
-
-            render_pass.set_bind_group(0, &self.rotation.bind_group, &[]);
-            render_pass.set_bind_group(1, &self.camera.bind_group, &[]);
-            render_pass.set_bind_group(2, &self.skybox.bind_group, &[]);
-            render_pass.set_bind_group(3, &self.texture.bind_group, &[]);
-            render_pass.set_pipeline(&self.skybox_pipeline);
-            render_pass.draw(0..3, 0..1);
-
-            drop(render_pass);
-            for shape in self.draw_shapes.iter_mut() {
-                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: None,
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view: &view,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Load, // Clear(wgpu::Color::default()),
-                            store: true,
-                        },
-                    })],
-                    depth_stencil_attachment: None,
-                });
-                render_pass.set_bind_group(0, &self.rotation.bind_group, &[]);
-                render_pass.set_bind_group(1, &self.camera.bind_group, &[]);
-                render_pass.set_bind_group(2, &self.skybox.bind_group, &[]);
-                render_pass.set_bind_group(3, &self.texture.bind_group, &[]);
-                render_pass.set_pipeline(&shape.pipeline);
-                render_pass.draw(0..shape.shape.vertex_count, 0..1);
-            }
-        }
-
-        /*
+        // Shapes take the first object slots, followed by models.
+        let transforms = self
+            .draw_shapes
+            .iter()
+            .map(|shape| &shape.shape.transform)
+            .chain(self.models.iter().map(|model| &model.transform));
+        self.objects
+            .write(&self.device, &self.queue, &self.rotation, transforms);
         for shape in self.draw_shapes.iter_mut() {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Load, // Clear(wgpu::Color::default()),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            });
-            render_pass.set_bind_group(0, &self.rotation.bind_group, &[]);
-            render_pass.set_bind_group(1, &self.camera.bind_group, &[]);
-            render_pass.set_bind_group(2, &self.skybox.bind_group, &[]);
-            render_pass.set_bind_group(3, &self.texture.bind_group, &[]);
-            render_pass.set_pipeline(&shape.pipeline);
-            render_pass.draw(0..shape.shape.vertex_count, 0..1);
+            shape.write_instances(&self.device, &self.queue);
         }
-        */
 
         // Egui
+        let mut camera_mode = self.camera_controller.mode();
+        let mut projection = self.projection.kind();
+        let mut path_action = None;
+        let mut bindings_action = None;
+        let mut params_changed = false;
         let full_output = self.egui_context.run(egui_input, |ctx| {
+            if !self.gui.visible {
+                return;
+            }
             egui::Area::new("space_gui")
                 //.fixed_pos(egui::pos2(10., 10.))
                 .show(ctx, |ui| {
                     ui.label("Hello egui!");
-                    ui.add(egui::Slider::new(&mut self.gui.slider, 0.0..=1.0).text("Slider"));
+                    ui.horizontal(|ui| {
+                        ui.label("Camera");
+                        ui.radio_value(&mut camera_mode, CameraMode::Fly, "Fly");
+                        ui.radio_value(&mut camera_mode, CameraMode::Orbit, "Orbit");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Projection");
+                        ui.radio_value(&mut projection, ProjectionKind::Perspective, "Perspective");
+                        ui.radio_value(
+                            &mut projection,
+                            ProjectionKind::InfiniteReverseZ,
+                            "Infinite",
+                        );
+                        ui.radio_value(&mut projection, ProjectionKind::Orthographic, "Ortho");
+                    });
+                    skybox_gui(ui, &mut self.gui, &self.skybox_source);
+                    clock_gui(ui, &mut self.clock);
+                    params_changed = params_gui(ui, &mut self.params);
+                    path_action = path_gui(ui, &mut self.gui, &mut self.camera_path);
+                    bindings_action = bindings_gui(ui, &mut self.gui, &mut self.bindings);
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if self.capture.is_recording() {
+                        ui.label("Recording frames");
+                    }
                 });
         });
 
+        self.set_camera_mode(camera_mode);
+        if projection != self.projection.kind() {
+            self.set_projection(projection);
+        }
+        if let Some(action) = path_action {
+            self.apply_path_action(action);
+        }
+        if params_changed {
+            self.write_params(0.0);
+        }
+        if let Some(action) = bindings_action {
+            self.apply_bindings_action(action);
+        }
+
         let clipped_primitives: Vec<egui::epaint::ClippedPrimitive> =
             self.egui_context.tessellate(full_output.shapes);
 
@@ -340,11 +1163,155 @@ impl RenderView {
             &screen_descriptor,
         );
 
+        let texture = match (&output, &self.target) {
+            (Some(output), _) => &output.texture,
+            (None, RenderTarget::Offscreen(texture)) => texture,
+            (None, RenderTarget::Surface(_)) => unreachable!(),
+        };
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.draw(&mut encoder, &view, &clipped_primitives, &screen_descriptor);
+
+        // Surface textures usually can't be copied from, so a captured frame
+        // is drawn a second time into an offscreen texture.
+        #[cfg(not(target_arch = "wasm32"))]
+        let capture_texture = match &self.target {
+            RenderTarget::Surface(_) if self.capture.is_pending() => {
+                let mut config = self.config.clone();
+                config.usage =
+                    wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC;
+                let texture = create_offscreen(&self.device, &config);
+                let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                self.draw(&mut encoder, &view, &clipped_primitives, &screen_descriptor);
+                Some(texture)
+            }
+            _ => None,
+        };
+
+        // Send queue to GPU
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.capture.is_pending() {
+            let texture = capture_texture.as_ref().unwrap_or(texture);
+            match capture::read_texture(&self.device, &self.queue, texture) {
+                Ok(image) => self.capture.save(&image),
+                Err(e) => log::error!("Frame capture failed: {}", e),
+            }
+        }
+
+        if let Some(output) = output {
+            output.present();
+        }
+
+        Ok(())
+    }
+
+    fn apply_path_action(&mut self, action: PathAction) {
+        match action {
+            PathAction::AddKeyframe => self.add_camera_keyframe(),
+            PathAction::Toggle => self.toggle_camera_path(),
+            PathAction::Clear => self.camera_path = PathPlayer::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            PathAction::Save => match self.camera_path.path.save(CAMERA_PATH_FILE) {
+                Ok(()) => log::info!("Saved camera path to {}", CAMERA_PATH_FILE),
+                Err(e) => log::error!("Failed to save {}: {}", CAMERA_PATH_FILE, e),
+            },
+            #[cfg(not(target_arch = "wasm32"))]
+            PathAction::Load => match CameraPath::read(CAMERA_PATH_FILE) {
+                Ok(path) => self.set_camera_path(path),
+                Err(e) => log::error!("Failed to load {}: {}", CAMERA_PATH_FILE, e),
+            },
+        }
+    }
+
+    fn apply_bindings_action(&mut self, action: BindingsAction) {
+        match action {
+            BindingsAction::Reset => self.bindings = Bindings::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            BindingsAction::Save => match self.bindings.save(BINDINGS_FILE) {
+                Ok(()) => log::info!("Saved key bindings to {}", BINDINGS_FILE),
+                Err(e) => log::error!("Failed to save {}: {}", BINDINGS_FILE, e),
+            },
+            #[cfg(not(target_arch = "wasm32"))]
+            BindingsAction::Load => match Bindings::read(BINDINGS_FILE) {
+                Ok(bindings) => self.bindings = bindings,
+                Err(e) => log::error!("Failed to load {}: {}", BINDINGS_FILE, e),
+            },
+        }
+    }
+
+    /// Read back the current contents of a headless view's offscreen texture.
+    pub fn read_frame(&self) -> anyhow::Result<image::RgbaImage> {
+        match &self.target {
+            RenderTarget::Offscreen(texture) => {
+                capture::read_texture(&self.device, &self.queue, texture)
+            }
+            RenderTarget::Surface(_) => anyhow::bail!("Only headless views can read frames"),
+        }
+    }
+
+    fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        clipped_primitives: &[egui::epaint::ClippedPrimitive],
+        screen_descriptor: &egui_wgpu::renderer::ScreenDescriptor,
+    ) {
+        {
+            let mut render_pass =
+                TrackedRenderPass::new(encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Scene"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::default()),
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &self.depth,
+                        depth_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(texture::far_depth(
+                                self.projection.is_reverse_z(),
+                            )),
+                            store: true,
+                        }),
+                        stencil_ops: None,
+                    }),
+                }));
+
+            render_pass.set_bind_group(0, &self.objects.bind_group, &[self.objects.offset(0)]);
+            render_pass.set_bind_group(1, &self.camera.bind_group, &[]);
+            render_pass.set_bind_group(2, &self.skybox.bind_group, &[]);
+            render_pass.set_bind_group(3, &self.material.bind_group, &[]);
+            render_pass.set_pipeline(&self.skybox_pipeline);
+            render_pass.draw(0..3, 0..1);
+
+            for (index, shape) in self.draw_shapes.iter().enumerate() {
+                render_pass.set_bind_group(
+                    0,
+                    &self.objects.bind_group,
+                    &[self.objects.offset(index)],
+                );
+                let material = shape.material.as_ref().unwrap_or(&self.material);
+                render_pass.set_bind_group(3, &material.bind_group, &[]);
+                shape.draw(&mut render_pass);
+            }
+
+            for (index, model) in self.models.iter().enumerate() {
+                let offset = self.objects.offset(self.draw_shapes.len() + index);
+                render_pass.set_bind_group(0, &self.objects.bind_group, &[offset]);
+                render_pass.set_pipeline(&self.model_pipeline);
+                model.draw(&mut render_pass);
+            }
+        }
+
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Load,
@@ -354,17 +1321,327 @@ impl RenderView {
                 depth_stencil_attachment: None,
             });
 
-            self.egui_renderer.render(
-                &mut render_pass,
-                clipped_primitives.as_slice(),
-                &screen_descriptor,
-            );
+            self.egui_renderer
+                .render(&mut render_pass, clipped_primitives, screen_descriptor);
         }
+    }
+}
 
-        // Send queue to GPU
-        self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
+/// Camera path buttons pressed in the GUI.
+enum PathAction {
+    AddKeyframe,
+    Toggle,
+    Clear,
+    #[cfg(not(target_arch = "wasm32"))]
+    Save,
+    #[cfg(not(target_arch = "wasm32"))]
+    Load,
+}
 
-        Ok(())
+fn path_gui(ui: &mut egui::Ui, gui: &mut GuiState, player: &mut PathPlayer) -> Option<PathAction> {
+    let mut action = None;
+    ui.collapsing("Camera path", |ui| {
+        ui.label(format!(
+            "{} keyframes, {:.1}s",
+            player.path.keyframes.len(),
+            player.path.duration()
+        ));
+        ui.add(egui::Slider::new(&mut gui.path_duration, 0.1..=10.0).text("Seconds"));
+        egui::ComboBox::from_label("Easing")
+            .selected_text(format!("{:?}", gui.path_easing))
+            .show_ui(ui, |ui| {
+                for easing in Easing::ALL {
+                    ui.selectable_value(&mut gui.path_easing, easing, format!("{:?}", easing));
+                }
+            });
+        repeat_gui(ui, &mut player.path.repeat);
+        ui.horizontal(|ui| {
+            if ui.button("Add keyframe").clicked() {
+                action = Some(PathAction::AddKeyframe);
+            }
+            let play = if player.is_playing() { "Stop" } else { "Play" };
+            if ui.button(play).clicked() {
+                action = Some(PathAction::Toggle);
+            }
+            if ui.button("Clear").clicked() {
+                action = Some(PathAction::Clear);
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui.button("Save").clicked() {
+                action = Some(PathAction::Save);
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui.button("Load").clicked() {
+                action = Some(PathAction::Load);
+            }
+        });
+    });
+    action
+}
+
+/// Key binding buttons pressed in the GUI.
+enum BindingsAction {
+    Reset,
+    #[cfg(not(target_arch = "wasm32"))]
+    Save,
+    #[cfg(not(target_arch = "wasm32"))]
+    Load,
+}
+
+fn bindings_gui(
+    ui: &mut egui::Ui,
+    gui: &mut GuiState,
+    bindings: &mut Bindings,
+) -> Option<BindingsAction> {
+    let mut action = None;
+    ui.collapsing("Key bindings", |ui| {
+        egui::Grid::new("bindings").show(ui, |ui| {
+            for bound in Action::ALL.into_iter().filter(|a| a.is_available()) {
+                ui.label(format!("{:?}", bound));
+                let text = if gui.rebinding == Some(bound) {
+                    String::from("Press a key...")
+                } else {
+                    let names: Vec<String> =
+                        bindings.get(bound).iter().map(|b| b.to_string()).collect();
+                    names.join(", ")
+                };
+                if ui.button(text).clicked() {
+                    gui.rebinding = Some(bound);
+                }
+                if ui.small_button("Clear").clicked() {
+                    bindings.clear(bound);
+                }
+                ui.end_row();
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Reset").clicked() {
+                action = Some(BindingsAction::Reset);
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui.button("Save").clicked() {
+                action = Some(BindingsAction::Save);
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui.button("Load").clicked() {
+                action = Some(BindingsAction::Load);
+            }
+        });
+    });
+    action
+}
+
+/// Pick a skybox from the bundled sets, or type a cross, strip or
+/// equirectangular image.
+fn skybox_gui(ui: &mut egui::Ui, gui: &mut GuiState, current: &SkyboxSource) {
+    let presets = [
+        ("CMB", SkyboxSource::default()),
+        (
+            "Sky",
+            SkyboxSource::Faces(
+                ["right", "left", "top", "bottom", "front", "back"]
+                    .map(|face| format!("skybox/{}.jpg", face)),
+            ),
+        ),
+        ("Starfield", SkyboxSource::Starfield(Starfield::default())),
+    ];
+    ui.horizontal(|ui| {
+        let selected = match presets.iter().find(|(_, source)| source == current) {
+            Some((name, _)) => name.to_string(),
+            None => match current {
+                SkyboxSource::Faces(faces) => faces[0].clone(),
+                SkyboxSource::Image(file_name) => file_name.clone(),
+                SkyboxSource::Equirect { file, .. } => file.clone(),
+                SkyboxSource::Starfield(starfield) => format!("Starfield {}", starfield.seed),
+            },
+        };
+        egui::ComboBox::from_label("Skybox")
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                for (name, source) in &presets {
+                    if ui.selectable_label(source == current, *name).clicked() {
+                        gui.skybox_choice = Some(source.clone());
+                    }
+                }
+            });
+        // Reseeding gives a new sky with the same look. Generating one takes
+        // a while, so it waits for the drag to end.
+        if let SkyboxSource::Starfield(starfield) = current {
+            let mut seed = gui.starfield_seed.unwrap_or(starfield.seed);
+            let response = ui.add(egui::DragValue::new(&mut seed).prefix("Seed "));
+            if response.changed() {
+                gui.starfield_seed = Some(seed);
+            }
+            if response.drag_released() || (response.changed() && !response.dragged()) {
+                if let Some(seed) = gui.starfield_seed.take() {
+                    gui.skybox_choice = Some(SkyboxSource::Starfield(Starfield {
+                        seed,
+                        ..starfield.clone()
+                    }));
+                }
+            }
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(&mut gui.skybox_path);
+        if ui.button("Load image").clicked() && !gui.skybox_path.is_empty() {
+            gui.skybox_choice = Some(SkyboxSource::Image(gui.skybox_path.clone()));
+        }
+        if ui.button("Load panorama").clicked() && !gui.skybox_path.is_empty() {
+            gui.skybox_choice = Some(SkyboxSource::Equirect {
+                file: gui.skybox_path.clone(),
+                size: None,
+            });
+        }
+    });
+}
+
+fn clock_gui(ui: &mut egui::Ui, clock: &mut SimClock) {
+    ui.horizontal(|ui| {
+        ui.label(format!("Time {:.2}s", clock.time().as_secs_f32()));
+        let pause = if clock.is_paused() { "Resume" } else { "Pause" };
+        if ui.button(pause).clicked() {
+            clock.toggle_pause();
+        }
+        if ui.button("Step").clicked() {
+            clock.single_step();
+        }
+    });
+    ui.add(egui::Slider::new(&mut clock.scale, 0.0..=4.0).text("Time scale"));
+}
+
+/// Inspect and edit the animated parameters, returning whether any changed.
+fn params_gui(ui: &mut egui::Ui, params: &mut Params) -> bool {
+    let time = params.time();
+    let mut changed = false;
+    ui.collapsing("Parameters", |ui| {
+        for param in params.params.iter_mut() {
+            let value = param.animation.sample(time);
+            ui.collapsing(format!("{} = {:.3}", param.name, value), |ui| {
+                changed |= animation_gui(ui, &param.name, &mut param.animation, value);
+            });
+        }
+    });
+    changed
+}
+
+fn animation_gui(ui: &mut egui::Ui, name: &str, animation: &mut Animation, value: f32) -> bool {
+    let before = animation.clone();
+    egui::ComboBox::from_id_source(name)
+        .selected_text(animation.kind())
+        .show_ui(ui, |ui| {
+            // A new kind starts from the current value.
+            let kinds = [
+                Animation::Constant(value),
+                Animation::Tween(Tween {
+                    from: value,
+                    ..Default::default()
+                }),
+                Animation::Noise(NoiseAnimation::default()),
+                Animation::Curve(Curve {
+                    keys: vec![CurveKey {
+                        time: 0.0,
+                        value,
+                        easing: Easing::default(),
+                        mode: EaseMode::default(),
+                    }],
+                    repeat: Repeat::Loop,
+                }),
+            ];
+            for kind in kinds {
+                let selected = kind.kind() == animation.kind();
+                if ui.selectable_label(selected, kind.kind()).clicked() && !selected {
+                    *animation = kind;
+                }
+            }
+        });
+
+    let drag = |ui: &mut egui::Ui, label: &str, value: &mut f32| {
+        ui.add(
+            egui::DragValue::new(value)
+                .speed(0.01)
+                .prefix(format!("{label} ")),
+        );
+    };
+    match animation {
+        Animation::Constant(value) => drag(ui, "Value", value),
+        Animation::Tween(tween) => {
+            ui.horizontal(|ui| {
+                drag(ui, "From", &mut tween.from);
+                drag(ui, "To", &mut tween.to);
+            });
+            ui.horizontal(|ui| {
+                drag(ui, "Delay", &mut tween.delay);
+                drag(ui, "Seconds", &mut tween.duration);
+            });
+            easing_gui(ui, name, &mut tween.easing, &mut tween.mode);
+            repeat_gui(ui, &mut tween.repeat);
+        }
+        Animation::Noise(noise) => {
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut noise.noise.seed).prefix("Seed "));
+                drag(ui, "Frequency", &mut noise.noise.frequency);
+                ui.add(
+                    egui::DragValue::new(&mut noise.noise.octaves)
+                        .clamp_range(1..=8)
+                        .prefix("Octaves "),
+                );
+            });
+            ui.horizontal(|ui| {
+                drag(ui, "Scale", &mut noise.scale);
+                drag(ui, "Offset", &mut noise.offset);
+                ui.checkbox(&mut noise.integrate, "Integrate");
+            });
+        }
+        Animation::Curve(curve) => {
+            let mut remove = None;
+            for (index, key) in curve.keys.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    drag(ui, "Time", &mut key.time);
+                    drag(ui, "Value", &mut key.value);
+                    if ui.small_button("Remove").clicked() {
+                        remove = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = remove {
+                curve.keys.remove(index);
+            }
+            if ui.button("Add key").clicked() {
+                let time = curve.keys.last().map_or(0.0, |k| k.time + 1.0);
+                curve.keys.push(CurveKey {
+                    time,
+                    value,
+                    easing: Easing::Sine,
+                    mode: EaseMode::default(),
+                });
+            }
+            curve.keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+            repeat_gui(ui, &mut curve.repeat);
+        }
     }
+    *animation != before
+}
+
+fn easing_gui(ui: &mut egui::Ui, id: &str, easing: &mut Easing, mode: &mut EaseMode) {
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_source((id, "easing"))
+            .selected_text(format!("{:?}", easing))
+            .show_ui(ui, |ui| {
+                for option in Easing::ALL {
+                    ui.selectable_value(easing, option, format!("{:?}", option));
+                }
+            });
+        ui.radio_value(mode, EaseMode::In, "In");
+        ui.radio_value(mode, EaseMode::Out, "Out");
+        ui.radio_value(mode, EaseMode::InOut, "In-out");
+    });
+}
+
+fn repeat_gui(ui: &mut egui::Ui, repeat: &mut Repeat) {
+    ui.horizontal(|ui| {
+        ui.radio_value(repeat, Repeat::Once, "Once");
+        ui.radio_value(repeat, Repeat::Loop, "Loop");
+        ui.radio_value(repeat, Repeat::PingPong, "Ping-pong");
+    });
 }
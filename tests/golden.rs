@@ -0,0 +1,964 @@
+//! Golden-image regression tests.
+//!
+//! Each scene is rendered headless with a fixed camera, rotation and noise
+//! jitter and compared against `tests/reference/<name>.png`. Mismatches write a diff
+//! image to the cargo target tmpdir. Run with `UPDATE_GOLDEN=1` to
+//! (re)generate the references after an intended change to the picture.
+
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
+};
+
+use cgmath::Deg;
+use space::{
+    bindings::{Bindings, Input},
+    camera::{CameraMode, ProjectionKind},
+    camera_path::CameraPath,
+    draw_shape::DrawShape,
+    input::InputEvent,
+    instance::Instance,
+    mesh::{ColorVertex, Mesh},
+    noise::Noise,
+    object::Transform,
+    replay::{Recorder, Recording, Replay},
+    scene,
+    skybox::SkyboxSource,
+    starfield::Starfield,
+    texture::{TextureOptions, Wrap},
+    view::RenderView,
+};
+use winit::event::{MouseButton, MouseScrollDelta, VirtualKeyCode};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 200;
+
+/// Largest per-channel difference that still counts as a matching pixel.
+const TOLERANCE: u8 = 8;
+
+/// Fraction of pixels allowed to exceed `TOLERANCE`, to absorb rasterization
+/// differences along triangle edges between adapters.
+const MAX_MISMATCHED: f64 = 0.002;
+
+struct Scene {
+    name: &'static str,
+    position: (f32, f32, f32),
+    yaw: Deg<f32>,
+    pitch: Deg<f32>,
+    /// Constant spin, or `None` to keep the animated one.
+    rotation: Option<Deg<f32>>,
+    /// Constant noise jitter, or `None` to keep the animated noise.
+    jitter: Option<[f32; 4]>,
+    shapes: Vec<DrawShape>,
+    models: Vec<&'static str>,
+}
+
+impl Scene {
+    fn new(name: &'static str) -> Self {
+        Scene {
+            name,
+            position: (0.0, 5.0, 10.0),
+            yaw: Deg(-90.0),
+            pitch: Deg(-20.0),
+            rotation: Some(Deg(0.0)),
+            jitter: Some([0.5; 4]),
+            shapes: Vec::new(),
+            models: Vec::new(),
+        }
+    }
+
+    /// The shapes of `res/scene.ron`.
+    fn pyramids(name: &'static str) -> Self {
+        Scene {
+            shapes: vec![background(), pyramid4(), pyramid()],
+            ..Scene::new(name)
+        }
+    }
+
+    /// The textured cube, seen from the front and turned 30 degrees.
+    fn cube(name: &'static str) -> Self {
+        Scene {
+            position: (0.0, 1.5, 3.0),
+            pitch: Deg(-25.0),
+            rotation: Some(Deg(30.0)),
+            models: vec!["cube/cube.obj"],
+            ..Scene::new(name)
+        }
+    }
+
+    fn view(self) -> RenderView {
+        let mut view = headless_view();
+        view.set_camera(self.position, self.yaw, self.pitch);
+        if let Some(rotation) = self.rotation {
+            view.set_rotation(rotation);
+        }
+        if let Some(jitter) = self.jitter {
+            view.set_jitter(jitter);
+        }
+        for shape in self.shapes {
+            view.push_shape(shape);
+        }
+        for file_name in self.models {
+            pollster::block_on(view.load_model(file_name)).unwrap();
+        }
+        view
+    }
+}
+
+fn headless_view() -> RenderView {
+    let mut view = new_headless();
+    view.set_gui_visible(false);
+    view
+}
+
+fn new_headless() -> RenderView {
+    pollster::block_on(RenderView::new_headless(WIDTH, HEIGHT))
+        .unwrap_or_else(|e| panic!("Golden tests need a GPU or software adapter: {e}"))
+}
+
+/// Shapes as placed in `res/scene.ron`.
+fn placed(mut shape: DrawShape, x: f32) -> DrawShape {
+    shape.transform.translation = [x, 0.0, 0.0];
+    shape
+}
+
+fn background() -> DrawShape {
+    placed(DrawShape::new("vs_background", "fs_texture", 6), 2.5)
+}
+
+fn pyramid() -> DrawShape {
+    placed(DrawShape::new("vs_pyramid", "fs_main", 9), 2.5)
+}
+
+fn pyramid4() -> DrawShape {
+    placed(DrawShape::new("vs_pyramid4", "fs_main", 12), -2.5)
+}
+
+fn reference_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("reference")
+        .join(format!("{name}.png"))
+}
+
+fn check(scene: Scene) {
+    check_with(scene, |_| {});
+}
+
+/// `check`, with `setup` changing the view before it renders.
+fn check_with(scene: Scene, setup: impl FnOnce(&mut RenderView)) {
+    let name = scene.name;
+    let mut view = scene.view();
+    setup(&mut view);
+    compare(name, &frame(&mut view));
+}
+
+fn frame(view: &mut RenderView) -> image::RgbaImage {
+    view.render(egui::RawInput::default()).unwrap();
+    view.read_frame().unwrap()
+}
+
+/// Load `scene` into `view` and render it.
+fn scene_frame(view: &mut RenderView, scene: &scene::Scene) -> image::RgbaImage {
+    pollster::block_on(view.load_scene(scene)).unwrap();
+    frame(view)
+}
+
+fn compare(name: &str, actual: &image::RgbaImage) {
+    let reference = reference_path(name);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(reference.parent().unwrap()).unwrap();
+        actual.save(&reference).unwrap();
+        return;
+    }
+
+    let expected = image::open(&reference)
+        .unwrap_or_else(|e| {
+            panic!("Missing reference {reference:?} ({e}), run with UPDATE_GOLDEN=1")
+        })
+        .into_rgba8();
+    assert_eq!(
+        expected.dimensions(),
+        actual.dimensions(),
+        "{name}: reference size differs"
+    );
+
+    let mut diff = image::RgbaImage::new(WIDTH, HEIGHT);
+    let mut mismatched = 0;
+    for ((e, a), d) in expected
+        .pixels()
+        .zip(actual.pixels())
+        .zip(diff.pixels_mut())
+    {
+        let delta =
+            e.0.iter()
+                .zip(a.0)
+                .map(|(e, a)| e.abs_diff(a))
+                .max()
+                .unwrap();
+        if delta > TOLERANCE {
+            mismatched += 1;
+            *d = image::Rgba([255, 0, 0, 255]);
+        } else {
+            // Faded copy of the expected image for context.
+            *d = image::Rgba([e[0] / 4, e[1] / 4, e[2] / 4, 255]);
+        }
+    }
+
+    let fraction = mismatched as f64 / (WIDTH * HEIGHT) as f64;
+    if fraction > MAX_MISMATCHED {
+        let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+        std::fs::create_dir_all(&dir).unwrap();
+        actual.save(dir.join(format!("{name}.actual.png"))).unwrap();
+        diff.save(dir.join(format!("{name}.diff.png"))).unwrap();
+        panic!("{name}: {mismatched} pixels differ from the reference, see {dir:?}");
+    }
+}
+
+#[test]
+fn sky() {
+    check(Scene::new("sky"));
+}
+
+#[test]
+fn default_scene() {
+    check(Scene::pyramids("default_scene"));
+}
+
+#[test]
+fn scene_file() {
+    let mut view = headless_view();
+    view.set_jitter([0.5; 4]);
+    let scene = pollster::block_on(scene::Scene::load("scene.ron")).unwrap();
+    compare("default_scene", &scene_frame(&mut view, &scene));
+}
+
+#[test]
+fn rotated_pyramids() {
+    let mut scene = Scene::new("rotated_pyramids");
+    scene.position = (0.0, 1.0, 8.0);
+    scene.pitch = Deg(-10.0);
+    scene.rotation = Some(Deg(60.0));
+    scene.shapes = vec![pyramid4(), pyramid()];
+    check(scene);
+}
+
+#[test]
+fn looking_up() {
+    let mut scene = Scene::new("looking_up");
+    scene.position = (0.0, 0.0, 0.0);
+    scene.yaw = Deg(0.0);
+    scene.pitch = Deg(60.0);
+    check(scene);
+}
+
+#[test]
+fn jittered_background() {
+    let mut scene = Scene::new("jittered_background");
+    scene.jitter = Some([1.0, -1.0, 0.0, 0.0]);
+    scene.shapes = vec![background()];
+    check(scene);
+}
+
+#[test]
+fn indexed_mesh() {
+    let vertex = |position, color| ColorVertex {
+        position,
+        color,
+        tex_coords: [0.0; 2],
+    };
+    let vertices = [
+        vertex([-1.0, -1.0, 0.0], [1.0, 0.0, 0.0, 1.0]),
+        vertex([1.0, -1.0, 0.0], [0.0, 1.0, 0.0, 1.0]),
+        vertex([1.0, 1.0, 0.0], [0.0, 0.0, 1.0, 1.0]),
+        vertex([-1.0, 1.0, 0.0], [1.0, 1.0, 1.0, 1.0]),
+    ];
+    let mesh = Mesh::new(&vertices, Some(vec![0, 1, 2, 0, 2, 3]));
+
+    let mut scene = Scene::new("indexed_mesh");
+    scene.position = (0.0, 0.0, 5.0);
+    scene.pitch = Deg(0.0);
+    scene.shapes = vec![DrawShape::with_mesh("vs_mesh", "fs_main", mesh)];
+    check(scene);
+}
+
+#[test]
+fn obj_model() {
+    check(Scene::cube("obj_model"));
+}
+
+#[test]
+fn gltf_model() {
+    // No scene camera, so the view looks through the camera in the file.
+    let mut view = headless_view();
+    view.set_rotation(Deg(20.0));
+    let scene = scene::Scene::parse(r#"Scene(models: ["pyramids.glb"])"#).unwrap();
+    compare("gltf_model", &scene_frame(&mut view, &scene));
+}
+
+#[test]
+fn gltf_metallic_roughness() {
+    // Metallic factors default to 1 and rely on the texture to lower them;
+    // the right half is a rough dielectric. The base color sampler repeats
+    // across and mirrors down, and the metallic-roughness one repeats.
+    check(Scene {
+        position: (0.0, 0.0, 3.0),
+        pitch: Deg(0.0),
+        models: vec!["tiles.glb"],
+        ..Scene::new("gltf_metallic_roughness")
+    });
+}
+
+#[test]
+fn placed_shapes() {
+    let mut scene = Scene::new("placed_shapes");
+    scene.position = (0.0, 1.0, 8.0);
+    scene.pitch = Deg(-10.0);
+    scene.rotation = Some(Deg(30.0));
+    let mut tall = DrawShape::new("vs_pyramid4", "fs_main", 12);
+    tall.transform = Transform {
+        translation: [-1.5, 0.5, 0.0],
+        rotation: [0.0, 0.0, 20.0],
+        scale: [1.0, 2.0, 1.0],
+    };
+    let mut flat = DrawShape::new("vs_pyramid", "fs_main", 9);
+    flat.transform = Transform {
+        translation: [1.5, -0.5, -2.0],
+        rotation: [0.0, 45.0, 0.0],
+        scale: [2.0, 0.5, 2.0],
+    };
+    scene.shapes = vec![tall, flat];
+    check(scene);
+}
+
+#[test]
+fn depth_test_off() {
+    // The near red quad ignores depth, so the far green one drawn after it
+    // still shows in front.
+    let quad = |z: f32, size: f32, color: &str, depth_test: bool| {
+        format!(
+            r#"(
+                vertex_fn: "vs_mesh",
+                fragment_fn: "fs_main",
+                mesh: (
+                    vertices: [
+                        (position: (-{size}, -{size}, {z}), color: {color}),
+                        (position: ({size}, -{size}, {z}), color: {color}),
+                        (position: ({size}, {size}, {z}), color: {color}),
+                        (position: (-{size}, {size}, {z}), color: {color}),
+                    ],
+                    indices: [0, 1, 2, 0, 2, 3],
+                ),
+                depth_test: {depth_test},
+            )"#
+        )
+    };
+    let scene = scene::Scene::parse(&format!(
+        "Scene(camera: (position: (0.0, 0.0, 5.0), yaw: -90.0, pitch: 0.0), shapes: [{}, {}])",
+        quad(1.0, 1.5, "(1.0, 0.0, 0.0, 1.0)", false),
+        quad(-1.0, 1.0, "(0.0, 1.0, 0.0, 1.0)", true),
+    ))
+    .unwrap();
+    let frame = scene_frame(&mut headless_view(), &scene);
+    assert_eq!(frame.get_pixel(WIDTH / 2, HEIGHT / 2).0, [0, 255, 0, 255]);
+    compare("depth_test_off", &frame);
+}
+
+#[test]
+fn placed_model() {
+    check_with(Scene::cube("placed_model"), |view| {
+        view.set_model_transform(
+            0,
+            Transform {
+                translation: [0.8, 0.0, -1.0],
+                rotation: [0.0, 0.0, 0.0],
+                scale: [0.5, 1.0, 0.5],
+            },
+        );
+    });
+}
+
+#[test]
+fn instanced_pyramids() {
+    let mut scene = Scene::new("instanced_pyramids");
+    scene.position = (0.0, 6.0, 9.0);
+    scene.pitch = Deg(-35.0);
+    scene.rotation = Some(Deg(15.0));
+    let mut shape = DrawShape::new("vs_pyramid4", "fs_main", 12);
+    shape.instances = (0..100)
+        .map(|i| {
+            let (row, column) = ((i / 10) as f32, (i % 10) as f32);
+            Instance {
+                transform: Transform {
+                    translation: [column - 4.5, 0.0, row - 4.5],
+                    scale: [0.6; 3],
+                    ..Transform::default()
+                },
+                tint: [column / 9.0, 1.0, row / 9.0, 1.0],
+                phase: i as f32 * 0.3,
+            }
+        })
+        .collect();
+    scene.shapes = vec![shape];
+    check(scene);
+}
+
+#[test]
+fn orbit_camera() {
+    // Drag a quarter turn around the cube, then zoom out.
+    check_with(Scene::cube("orbit_camera"), |view| {
+        view.set_camera_mode(CameraMode::Orbit);
+        view.camera_controller.process_mouse(-40.0, 10.0);
+        view.update(Duration::from_millis(100));
+        view.camera_controller
+            .process_scroll(&MouseScrollDelta::LineDelta(0.0, -1.0));
+        view.update(Duration::from_millis(30));
+    });
+}
+
+#[test]
+fn camera_path() {
+    // Halfway along a linear move from the obj_model view to a side view.
+    let path = CameraPath::parse(
+        r#"(
+            keyframes: [
+                (position: (0.0, 1.5, 3.0), yaw: -90.0, pitch: -25.0),
+                (position: (3.0, 1.5, 0.0), yaw: -180.0, pitch: -25.0, duration: 2.0),
+            ],
+        )"#,
+    )
+    .unwrap();
+    check_with(Scene::cube("camera_path"), |view| {
+        view.set_camera_path(path);
+        view.update(Duration::from_secs(1));
+    });
+}
+
+#[test]
+fn infinite_reverse_z() {
+    // Same picture as the default scene, with depth running the other way.
+    check_with(Scene::pyramids("default_scene"), |view| {
+        view.set_projection(ProjectionKind::InfiniteReverseZ);
+    });
+}
+
+#[test]
+fn orthographic() {
+    check_with(Scene::cube("orthographic"), |view| {
+        view.set_projection(ProjectionKind::Orthographic);
+        view.set_ortho_height(4.0);
+    });
+}
+
+#[test]
+fn rebound_keys() {
+    // Rise with a rebound key, then strafe with a default one.
+    let shipped = pollster::block_on(Bindings::load("bindings.ron")).unwrap();
+    assert_eq!(shipped, Bindings::default());
+
+    check_with(Scene::cube("rebound_keys"), |view| {
+        view.bindings = Bindings::parse("{ move_up: [(input: Key(E))] }").unwrap();
+        for (key, millis) in [
+            (VirtualKeyCode::Q, 0),
+            (VirtualKeyCode::E, 250),
+            (VirtualKeyCode::D, 150),
+        ] {
+            assert_eq!(view.process_input(Input::Key(key), true), millis > 0);
+            view.update(Duration::from_millis(millis));
+            view.process_input(Input::Key(key), false);
+        }
+    });
+}
+
+#[test]
+fn replayed_input() {
+    // Walk forward while dragging the view, saved and read back before replay.
+    let button = |input, pressed| InputEvent::Button { input, pressed };
+    let mut recorder = Recorder::new(None, Bindings::default());
+    recorder.record(button(Input::Key(VirtualKeyCode::W), true));
+    recorder.record(button(Input::Mouse(MouseButton::Left), true));
+    for _ in 0..10 {
+        recorder.record(InputEvent::MouseMotion(-3.0, 1.0));
+        recorder.end_frame(Duration::from_millis(16));
+    }
+    recorder.record(button(Input::Key(VirtualKeyCode::W), false));
+    recorder.record(button(Input::Mouse(MouseButton::Left), false));
+    recorder.record(InputEvent::MouseMotion(50.0, 50.0));
+    recorder.end_frame(Duration::from_millis(16));
+
+    let file = Path::new(env!("CARGO_TARGET_TMPDIR")).join("replayed_input.ron");
+    recorder.recording.save(&file).unwrap();
+    let recording = Recording::read(&file).unwrap();
+    assert_eq!(recording, recorder.recording);
+
+    let scene = Scene {
+        rotation: Some(Deg(0.0)),
+        ..Scene::cube("replayed_input")
+    };
+    check_with(scene, |view| {
+        let mut replay = Replay::new(recording);
+        replay.run(view).unwrap();
+        assert!(replay.is_finished());
+    });
+}
+
+#[test]
+fn replayed_gui() {
+    // Click the overlay's Orbit camera button over a few frames.
+    let pos = egui::pos2(130.0, 40.0);
+    let button = |pressed| egui::Event::PointerButton {
+        pos,
+        button: egui::PointerButton::Primary,
+        pressed,
+        modifiers: egui::Modifiers::default(),
+    };
+    let frames = [
+        vec![egui::Event::PointerMoved(pos)],
+        vec![button(true)],
+        vec![button(false)],
+    ];
+
+    let mut view = new_headless();
+    let mut recorder = Recorder::new(None, Bindings::default());
+    for events in frames {
+        let dt = Duration::from_millis(16);
+        let gui = egui::RawInput {
+            events,
+            ..Default::default()
+        };
+        view.update(dt);
+        recorder.record_gui(&gui);
+        recorder.end_frame(dt);
+        view.render(gui).unwrap();
+    }
+    assert_eq!(view.camera_controller.mode(), CameraMode::Orbit);
+
+    let file = Path::new(env!("CARGO_TARGET_TMPDIR")).join("replayed_gui.ron");
+    recorder.recording.save(&file).unwrap();
+    let recording = Recording::read(&file).unwrap();
+    assert_eq!(recording, recorder.recording);
+
+    // GL views share a display, so only one is alive at a time.
+    drop(view);
+    let mut view = new_headless();
+    assert_eq!(view.camera_controller.mode(), CameraMode::Fly);
+    Replay::new(recording).run(&mut view).unwrap();
+    assert_eq!(view.camera_controller.mode(), CameraMode::Orbit);
+}
+
+#[test]
+fn fixed_timestep() {
+    // One simulated second at different frame rates, time scales and pauses.
+    let runs: [(u64, u32, f32); 3] = [
+        (16_666_666, 60, 1.0),
+        (50_000_000, 20, 1.0),
+        (50_000_000, 10, 2.0),
+    ];
+    for (nanos, frames, scale) in runs {
+        let scene = Scene {
+            rotation: None,
+            jitter: None,
+            ..Scene::pyramids("fixed_timestep")
+        };
+        check_with(scene, |view| {
+            view.keys.rotation = true;
+            view.clock.scale = scale;
+            for _ in 0..frames {
+                view.update(Duration::from_nanos(nanos));
+            }
+            view.clock.set_paused(true);
+            view.update(Duration::from_millis(500));
+            assert_eq!(view.clock.time(), view.clock.step() * 60);
+        });
+    }
+}
+
+#[test]
+fn seeked_noise() {
+    // Seeking lands where running the clock does, and the spin is the
+    // integral of its speed noise.
+    let noise = Noise::new(7, 3.0, 4);
+    let dt = 1e-4;
+    let sum: f32 = (0..20_000)
+        .map(|i| noise.sample((i as f32 + 0.5) * dt) * dt)
+        .sum();
+    assert!((noise.integral(2.0) - sum).abs() < 1e-3);
+
+    let scene = Scene {
+        rotation: None,
+        jitter: None,
+        ..Scene::pyramids("fixed_timestep")
+    };
+    check_with(scene, |view| view.seek_params(1.0));
+}
+
+#[test]
+fn animated_params() {
+    // Half a second into a spin tween, held for the first half second, and
+    // a second into a jitter curve and an alpha fade.
+    let scene = scene::Scene::parse(
+        r#"Scene(
+            params: {
+                "spin": Tween(from: 0.0, to: 1.5, duration: 1.0, easing: Linear),
+                "jitter_r": Curve(keys: [(time: 0.0, value: 0.0), (time: 1.0, value: 2.0)]),
+                "jitter_g": Constant(0.0),
+                "alpha": Tween(from: 1.0, to: 0.2, duration: 1.0, easing: Linear),
+            },
+        )"#,
+    )
+    .unwrap();
+    let mut view = headless_view();
+    pollster::block_on(view.load_scene(&scene)).unwrap();
+    for shape in [background(), pyramid4(), pyramid()] {
+        view.push_shape(shape);
+    }
+    // With rotation off the spin holds while the other parameters run.
+    for _ in 0..30 {
+        view.update(view.clock.step());
+    }
+    assert_eq!(view.params.value("spin", 0.0), Some(0.0));
+    let alpha = view.params.value("alpha", 0.0).unwrap();
+    assert!((alpha - 0.6).abs() < 1e-5);
+    view.keys.rotation = true;
+    for _ in 0..30 {
+        view.update(view.clock.step());
+    }
+    let spin = view.params.value("spin", 0.0).unwrap();
+    assert!((spin - 0.75).abs() < 1e-5);
+    compare("animated_params", &frame(&mut view));
+}
+
+/// A scene with a quad at `x` = -1.1 and 1.1 for each of `fields`, its
+/// texture tiled three times.
+fn tiled_quads(fields: [&str; 2]) -> scene::Scene {
+    tiled_texture_quads("baba.png", fields)
+}
+
+/// `tiled_quads` with `texture` in place of `baba.png`.
+fn tiled_texture_quads(texture: &str, fields: [&str; 2]) -> scene::Scene {
+    let quad = |x: f32, fields: &str| {
+        format!(
+            r#"(
+                vertex_fn: "vs_mesh",
+                fragment_fn: "fs_texture",
+                mesh: (
+                    vertices: [
+                        (position: ({l}, -1.0, 0.0), color: (1.0, 1.0, 1.0, 1.0), tex_coords: (0.0, 3.0)),
+                        (position: ({r}, -1.0, 0.0), color: (1.0, 1.0, 1.0, 1.0), tex_coords: (3.0, 3.0)),
+                        (position: ({r}, 1.0, 0.0), color: (1.0, 1.0, 1.0, 1.0), tex_coords: (3.0, 0.0)),
+                        (position: ({l}, 1.0, 0.0), color: (1.0, 1.0, 1.0, 1.0), tex_coords: (0.0, 0.0)),
+                    ],
+                    indices: [0, 1, 2, 0, 2, 3],
+                ),
+                texture: "{texture}",
+                {fields}
+            )"#,
+            l = x - 1.0,
+            r = x + 1.0,
+        )
+    };
+    scene::Scene::parse(&format!(
+        "Scene(camera: (position: (0.0, 0.0, 5.0), yaw: -90.0, pitch: 0.0), shapes: [{}, {}])",
+        quad(-1.1, fields[0]),
+        quad(1.1, fields[1]),
+    ))
+    .unwrap()
+}
+
+#[test]
+fn texture_options() {
+    // Repeated and magnified without filtering on the left, mirrored on the right.
+    let scene = tiled_quads([
+        "texture_options: (wrap_u: Repeat, wrap_v: Repeat, mag_filter: Nearest),",
+        "texture_options: (wrap_u: Mirror, wrap_v: Mirror, min_filter: Linear,
+        mipmap_filter: Linear, anisotropy: 16),",
+    ]);
+    let mut view = headless_view();
+    compare("texture_options", &scene_frame(&mut view, &scene));
+
+    // Anisotropy needs linear filtering.
+    let scene = tiled_quads(["", "texture_options: (anisotropy: 4),"]);
+    assert!(pollster::block_on(view.load_scene(&scene)).is_err());
+}
+
+#[test]
+fn shared_texture_materials() {
+    // One texture, tinted and turned on the left, shifted and glowing on the right.
+    let scene = tiled_quads([
+        "texture_options: (wrap_u: Repeat, wrap_v: Repeat),
+        material: (tint: (1.0, 0.4, 0.4), uv: (scale: (0.5, 0.5), rotation: 90.0)),",
+        "texture_options: (wrap_u: Repeat, wrap_v: Repeat),
+        material: (uv: (offset: (0.5, 0.0)), emissive: (0.0, 0.0, 0.5)),",
+    ]);
+    let mut view = headless_view();
+    compare("shared_texture_materials", &scene_frame(&mut view, &scene));
+
+    // Loaded once per file and options.
+    let repeat = TextureOptions {
+        wrap_u: Wrap::Repeat,
+        wrap_v: Wrap::Repeat,
+        ..Default::default()
+    };
+    let mut load = |options| pollster::block_on(view.load_texture("baba.png", &options)).unwrap();
+    let repeated = load(repeat);
+    let clamped = load(TextureOptions::default());
+    assert!(Rc::ptr_eq(&repeated, &load(repeat)));
+    assert!(!Rc::ptr_eq(&repeated, &clamped));
+
+    // Until a scene replaces the shapes using them.
+    let (weak_repeated, weak_clamped) = (Rc::downgrade(&repeated), Rc::downgrade(&clamped));
+    drop((repeated, clamped));
+    pollster::block_on(view.load_scene(&tiled_quads(["", ""]))).unwrap();
+    assert!(weak_repeated.upgrade().is_none());
+    assert!(weak_clamped.upgrade().is_some());
+}
+
+#[test]
+fn premultiplied_alpha() {
+    // Red to orange, fading in from the left.
+    let image = image::RgbaImage::from_fn(16, 16, |x, y| {
+        image::Rgba([255, y as u8 * 8, 0, x as u8 * 17])
+    });
+    let file = Path::new(env!("CARGO_TARGET_TMPDIR")).join("fade.png");
+    image.save(&file).unwrap();
+    let file = file.to_str().unwrap();
+
+    // Linear data on the left, sRGB on the right, at half opacity.
+    let mut view = headless_view();
+    let mut render = |premultiply: bool| {
+        let fields = |srgb: bool| {
+            format!(
+                "texture_options: (wrap_u: Repeat, wrap_v: Repeat, srgb: {srgb}, \
+                premultiply_alpha: {premultiply}), material: (opacity: 0.5),"
+            )
+        };
+        let scene = tiled_texture_quads(file, [&fields(false), &fields(true)]);
+        scene_frame(&mut view, &scene)
+    };
+    let straight = render(false);
+    let premultiplied = render(true);
+    compare("premultiplied_alpha", &premultiplied);
+
+    // Blending premultiplied colors gives the same picture as straight alpha.
+    let mismatched = straight
+        .pixels()
+        .zip(premultiplied.pixels())
+        .filter(|(a, b)| a.0.iter().zip(b.0).any(|(a, b)| a.abs_diff(b) > TOLERANCE))
+        .count();
+    assert!(mismatched as f64 <= MAX_MISMATCHED * (WIDTH * HEIGHT) as f64);
+}
+
+/// The cmb faces in the order right, left, top, bottom, front, back.
+fn cmb_faces() -> Vec<image::RgbaImage> {
+    ["right", "left", "top", "bottom", "front", "back"]
+        .iter()
+        .map(|face| {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("res")
+                .join("cmb")
+                .join(format!("cmb_{face}.png"));
+            image::load_from_memory(&std::fs::read(path).unwrap())
+                .unwrap()
+                .into_rgba8()
+        })
+        .collect()
+}
+
+/// Lay `faces` out on a grid of `columns` x `rows` cells at `cells`.
+fn layout(faces: &[image::RgbaImage], columns: u32, rows: u32, cells: [(u32, u32); 6]) -> PathBuf {
+    let size = faces[0].width();
+    let mut image = image::RgbaImage::new(size * columns, size * rows);
+    for (face, (column, row)) in faces.iter().zip(cells) {
+        image::imageops::replace(
+            &mut image,
+            face,
+            (column * size) as i64,
+            (row * size) as i64,
+        );
+    }
+    let file = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("skybox_{columns}x{rows}.png"));
+    image.save(&file).unwrap();
+    file
+}
+
+#[test]
+fn cross_skybox() {
+    let mut faces = cmb_faces();
+    let horizontal = layout(
+        &faces,
+        4,
+        3,
+        [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)],
+    );
+    let strip = layout(
+        &faces,
+        6,
+        1,
+        [(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0)],
+    );
+    faces[5] = image::imageops::rotate180(&faces[5]);
+    let vertical = layout(
+        &faces,
+        3,
+        4,
+        [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (1, 3)],
+    );
+
+    // Every layout cuts back into the same faces as the separate files.
+    for file in [horizontal, vertical, strip] {
+        let source = SkyboxSource::Image(file.to_str().unwrap().to_string());
+        check_with(Scene::new("sky"), |view| {
+            pollster::block_on(view.load_skybox(&source)).unwrap();
+        });
+    }
+}
+
+#[test]
+fn mismatched_skybox() {
+    let faces = cmb_faces();
+    let mut image = image::RgbaImage::new(faces[0].width() * 5, faces[0].height());
+    image::imageops::replace(&mut image, &faces[0], 0, 0);
+    let file = Path::new(env!("CARGO_TARGET_TMPDIR")).join("skybox_5x1.png");
+    image.save(&file).unwrap();
+
+    let mut view = headless_view();
+    let source = SkyboxSource::Image(file.to_str().unwrap().to_string());
+    assert!(pollster::block_on(view.load_skybox(&source)).is_err());
+    // The previous skybox stays in place.
+    assert_eq!(view.skybox_source, SkyboxSource::default());
+}
+
+#[test]
+fn failed_scene_load() {
+    let scene = scene::Scene::parse(
+        r#"Scene(
+            skybox: Some(Starfield(size: 16)),
+            models: ["missing.obj"],
+            params: {"alpha": Constant(0.5)},
+        )"#,
+    )
+    .unwrap();
+    let mut view = headless_view();
+    let alpha = view.params.value("alpha", 0.0);
+    assert!(pollster::block_on(view.load_scene(&scene)).is_err());
+    // Nothing of the new scene replaces the previous one.
+    assert_eq!(view.skybox_source, SkyboxSource::default());
+    assert_eq!(view.params.value("alpha", 0.0), alpha);
+}
+
+/// Linear color of a 2:1 panorama at `u`, `v`: a hue per quarter of
+/// longitude, darker towards the bottom, and lines every 30 degrees brighter
+/// than white.
+fn panorama_color(u: f32, v: f32) -> [f32; 3] {
+    let on_line = |t: f32, cells: f32| ((t * cells + 0.5).fract() - 0.5).abs() < 0.02;
+    if on_line(u, 12.0) || on_line(v, 6.0) {
+        return [4.0; 3];
+    }
+    let hues = [
+        [1.0, 0.2, 0.2],
+        [0.2, 1.0, 0.2],
+        [0.2, 0.2, 1.0],
+        [1.0, 1.0, 0.2],
+    ];
+    let hue = hues[(u * 4.0) as usize % 4];
+    hue.map(|c| c * (1.0 - 0.8 * v))
+}
+
+/// Save the panorama as Radiance HDR and as an sRGB PNG.
+fn write_panoramas() -> (PathBuf, PathBuf) {
+    let (width, height) = (384, 192);
+    let colors: Vec<[f32; 3]> = (0..height)
+        .flat_map(|y| {
+            (0..width).map(move |x| {
+                let u = (x as f32 + 0.5) / width as f32;
+                let v = (y as f32 + 0.5) / height as f32;
+                panorama_color(u, v)
+            })
+        })
+        .collect();
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+
+    let hdr = dir.join("panorama.hdr");
+    let pixels: Vec<image::Rgb<f32>> = colors.iter().map(|&c| image::Rgb(c)).collect();
+    image::codecs::hdr::HdrEncoder::new(std::fs::File::create(&hdr).unwrap())
+        .encode(&pixels, width, height)
+        .unwrap();
+
+    let png = dir.join("panorama.png");
+    let srgb = |c: f32| {
+        let c = c.clamp(0.0, 1.0);
+        let c = if c <= 0.0031308 {
+            c * 12.92
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        };
+        (c * 255.0).round() as u8
+    };
+    let bytes = colors.iter().flat_map(|c| c.map(srgb)).collect();
+    image::RgbImage::from_raw(width as u32, height as u32, bytes)
+        .unwrap()
+        .save(&png)
+        .unwrap();
+    (hdr, png)
+}
+
+#[test]
+fn equirect_skybox() {
+    // Filtering the float panorama spreads its over-bright lines wider than
+    // those of the 8 bit one, which clip to white before projection.
+    let (hdr, png) = write_panoramas();
+    for (name, file) in [("equirect_hdr", hdr), ("equirect_ldr", png)] {
+        let source = SkyboxSource::Equirect {
+            file: file.to_str().unwrap().to_string(),
+            size: Some(128),
+        };
+        let scene = Scene {
+            yaw: Deg(-60.0),
+            pitch: Deg(20.0),
+            ..Scene::new(name)
+        };
+        check_with(scene, |view| {
+            pollster::block_on(view.load_skybox(&source)).unwrap();
+        });
+    }
+}
+
+fn starfield_frame(starfield: Starfield) -> image::RgbaImage {
+    let mut view = Scene::new("starfield_skybox").view();
+    pollster::block_on(view.load_skybox(&SkyboxSource::Starfield(starfield))).unwrap();
+    frame(&mut view)
+}
+
+#[test]
+fn starfield_skybox() {
+    let starfield = Starfield {
+        seed: 7,
+        size: 256,
+        stars: 3000,
+        star_size: 0.3,
+        ..Default::default()
+    };
+    let frame = starfield_frame(starfield.clone());
+    compare("starfield_skybox", &frame);
+
+    // The seed alone decides the sky.
+    assert_eq!(starfield_frame(starfield.clone()), frame);
+    let reseeded = Starfield {
+        seed: 8,
+        ..starfield
+    };
+    assert_ne!(starfield_frame(reseeded), frame);
+}
+
+#[test]
+fn distant_model() {
+    // The checker texture, minified about ten times, is read from the mip
+    // chain instead of breaking into moiré.
+    check(Scene {
+        position: (0.0, 3.0, 7.0),
+        pitch: Deg(-22.0),
+        ..Scene::cube("distant_model")
+    });
+}